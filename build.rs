@@ -0,0 +1,299 @@
+//! Code-generates the `WasmInstruction` enum and its leaf-opcode decoders
+//! from `instructions.in`, so the enum and the decoder can't silently
+//! drift apart as opcodes are added.
+//!
+//! `include!` can only splice a complete item or expression at its call
+//! site, not a fragment into the middle of an existing `enum { .. }` or
+//! `match { .. }` — "macros cannot expand to enum variants" is a hard
+//! language limitation, not a version quirk. So each generated file is a
+//! full, standalone item:
+//!   - `instruction_variants.rs`: the *entire* `WasmInstruction` enum
+//!     (hand-written control-flow variants plus the table-driven leaf
+//!     variants), `include!`d as the whole enum declaration in
+//!     `src/ast.rs`.
+//!   - `instruction_dispatch.rs`: a full `fn decode_leaf(..)` matching on
+//!     the primary opcode byte, `include!`d at module scope in
+//!     `src/lib.rs` and called from one arm of `instruction_body`'s
+//!     `match opcode { .. }`.
+//!   - `instruction_dispatch_fc.rs`: a full `fn decode_leaf_fc(..)`
+//!     matching on the `0xFC` sub-opcode, `include!`d the same way and
+//!     called from `instruction_body`'s `0xFC` arm.
+//!
+//! A malformed table, a duplicate opcode, or a gap in the `0xFC`
+//! sub-opcode range is reported by emitting a `compile_error!` into the
+//! generated file instead of failing the build script itself, so the
+//! error surfaces at the `include!` call site with a normal diagnostic.
+
+use std::collections::HashSet;
+use std::env;
+use std::fs;
+use std::path::Path;
+
+struct Instruction {
+    variant: String,
+    kind: String,
+}
+
+enum Opcode {
+    Plain(u8),
+    Fc(u32),
+}
+
+fn parse_opcode(token: &str) -> Option<Opcode> {
+    if let Some((prefix, sub)) = token.split_once(':') {
+        let prefix = u8::from_str_radix(prefix.trim_start_matches("0x"), 16).ok()?;
+        if prefix != 0xFC {
+            return None;
+        }
+        Some(Opcode::Fc(sub.parse().ok()?))
+    } else {
+        Some(Opcode::Plain(
+            u8::from_str_radix(token.trim_start_matches("0x"), 16).ok()?,
+        ))
+    }
+}
+
+/// The variant declaration for one table entry, e.g. `LocalGet(u32),`.
+///
+/// Assumes `instr.kind` has already been validated (see the `KNOWN_KINDS`
+/// check in `main`), so an unrecognized kind here is a bug in that check
+/// rather than something a malformed `instructions.in` can trigger.
+fn variant_decl(instr: &Instruction) -> String {
+    let field = match instr.kind.as_str() {
+        "none" | "reserved" => return format!("    {},\n", instr.variant),
+        "localidx" | "globalidx" => "u32",
+        "memarg" => "WasmMemoryArg",
+        "i32" => "i32",
+        "i64" => "i64",
+        "f32" => "f32",
+        "f64" => "f64",
+        other => unreachable!("operand kind `{}` should have been rejected already", other),
+    };
+    format!("    {}({}),\n", instr.variant, field)
+}
+
+/// The match arm decoding one table entry, reading its operand from
+/// `input_var` (the slice already positioned just past the opcode byte
+/// at this match's call site).
+///
+/// Assumes `instr.kind` has already been validated (see the `KNOWN_KINDS`
+/// check in `main`), so an unrecognized kind here is a bug in that check
+/// rather than something a malformed `instructions.in` can trigger.
+fn dispatch_arm(pattern: &str, instr: &Instruction, input_var: &str) -> String {
+    let variant = &instr.variant;
+    let body = match instr.kind.as_str() {
+        "none" => format!("Ok(({}, {}))", input_var, variant),
+        "reserved" => format!("tag_(0x00)({}).map_output(|_| {})", input_var, variant),
+        "localidx" => format!("localidx({}).map_output({})", input_var, variant),
+        "globalidx" => format!("globalidx({}).map_output({})", input_var, variant),
+        "memarg" => format!("memarg({}).map_output({})", input_var, variant),
+        "i32" => format!(
+            "signed_int(32, {}).map_output(|v| {}(v as i32))",
+            input_var, variant
+        ),
+        "i64" => format!("signed_int(64, {}).map_output({})", input_var, variant),
+        "f32" => format!("f32({}).map_output({})", input_var, variant),
+        "f64" => format!("f64({}).map_output({})", input_var, variant),
+        other => unreachable!("operand kind `{}` should have been rejected already", other),
+    };
+    format!("        {} => {},\n", pattern, body)
+}
+
+/// The operand kinds `variant_decl`/`dispatch_arm` know how to generate
+/// code for; any other `kind` column in `instructions.in` is a malformed
+/// table, reported the same way as every other malformed-table case.
+const KNOWN_KINDS: &[&str] = &[
+    "none", "reserved", "localidx", "globalidx", "memarg", "i32", "i64", "f32", "f64",
+];
+
+/// Control-flow and call variants: hand-written rather than
+/// table-driven (see `instructions.in`'s header comment), but their
+/// *declarations* still have to live in the generated enum alongside the
+/// leaf variants so the whole `WasmInstruction` enum is one complete
+/// item. Their decode arms stay hand-written in `instruction_body`.
+const CONTROL_VARIANTS: &str = "\
+    Unreachable,
+    Nop,
+    Block {
+        block_type: WasmBlockType,
+        instructions: Vec<WasmInstruction>,
+    },
+    Loop {
+        block_type: WasmBlockType,
+        instructions: Vec<WasmInstruction>,
+    },
+    If {
+        block_type: WasmBlockType,
+        consequent: Vec<WasmInstruction>,
+        alternative: Vec<WasmInstruction>,
+    },
+    Jump {
+        label: u32,
+    },
+    JumpIf {
+        label: u32,
+    },
+    JumpTable {
+        locations: Vec<u32>,
+        label: u32,
+    },
+    Return,
+    Call {
+        function_index: u32,
+    },
+    CallIndirect {
+        type_index: u32,
+    },
+";
+
+fn write_compile_error(out_dir: &str, message: &str) {
+    let compile_error = format!("compile_error!({:?});\n", message);
+    for file in [
+        "instruction_variants.rs",
+        "instruction_dispatch.rs",
+        "instruction_dispatch_fc.rs",
+    ] {
+        fs::write(Path::new(out_dir).join(file), &compile_error).unwrap();
+    }
+}
+
+fn main() {
+    let manifest_dir = env::var("CARGO_MANIFEST_DIR").unwrap();
+    let out_dir = env::var("OUT_DIR").unwrap();
+    let table_path = Path::new(&manifest_dir).join("instructions.in");
+    println!("cargo:rerun-if-changed=instructions.in");
+
+    let table = fs::read_to_string(&table_path).expect("failed to read instructions.in");
+
+    let mut plain: Vec<(u8, Instruction)> = Vec::new();
+    let mut fc: Vec<(u32, Instruction)> = Vec::new();
+    let mut seen_plain = HashSet::new();
+    let mut seen_fc = HashSet::new();
+    let mut error: Option<String> = None;
+
+    for (lineno, line) in table.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let fields: Vec<&str> = line.split_whitespace().collect();
+        let [opcode_tok, variant, kind] = fields.as_slice() else {
+            error = Some(format!(
+                "instructions.in:{}: expected `<opcode> <Variant> <kind>`, found `{}`",
+                lineno + 1,
+                line
+            ));
+            break;
+        };
+        let Some(opcode) = parse_opcode(opcode_tok) else {
+            error = Some(format!(
+                "instructions.in:{}: invalid opcode `{}`",
+                lineno + 1,
+                opcode_tok
+            ));
+            break;
+        };
+        if !KNOWN_KINDS.contains(kind) {
+            error = Some(format!(
+                "instructions.in:{}: unknown operand kind `{}`",
+                lineno + 1,
+                kind
+            ));
+            break;
+        }
+        let instr = Instruction {
+            variant: variant.to_string(),
+            kind: kind.to_string(),
+        };
+        match opcode {
+            Opcode::Plain(b) => {
+                if !seen_plain.insert(b) {
+                    error = Some(format!(
+                        "instructions.in:{}: duplicate opcode 0x{:02X} (also used by an earlier entry)",
+                        lineno + 1,
+                        b
+                    ));
+                    break;
+                }
+                plain.push((b, instr));
+            }
+            Opcode::Fc(n) => {
+                if !seen_fc.insert(n) {
+                    error = Some(format!(
+                        "instructions.in:{}: duplicate 0xFC sub-opcode {} (also used by an earlier entry)",
+                        lineno + 1,
+                        n
+                    ));
+                    break;
+                }
+                fc.push((n, instr));
+            }
+        }
+    }
+
+    // The 0xFC sub-opcode space is a small contiguous range in the spec;
+    // a gap almost certainly means an entry was forgotten rather than
+    // that the opcode is intentionally reserved.
+    if error.is_none() {
+        if let Some(&max) = seen_fc.iter().max() {
+            for n in 0..=max {
+                if !seen_fc.contains(&n) {
+                    error = Some(format!(
+                        "instructions.in: missing 0xFC sub-opcode {} (sub-opcodes 0..={} must be contiguous)",
+                        n, max
+                    ));
+                    break;
+                }
+            }
+        }
+    }
+
+    if let Some(message) = error {
+        write_compile_error(&out_dir, &message);
+        return;
+    }
+
+    let mut variants = format!(
+        "#[derive(Clone, Debug, PartialEq)]\npub enum WasmInstruction {{\n{}",
+        CONTROL_VARIANTS
+    );
+    for (_, instr) in &plain {
+        variants.push_str(&variant_decl(instr));
+    }
+    for (_, instr) in &fc {
+        variants.push_str(&variant_decl(instr));
+    }
+    variants.push_str("}\n");
+
+    let mut dispatch = String::from(
+        "fn decode_leaf<'t>(opcode: u8, opcode_start: &'t [u8], input: &'t [u8]) -> IParserResult<'t, WasmInstruction> {\n    use WasmInstruction::*;\n    match opcode {\n",
+    );
+    for (opcode, instr) in &plain {
+        dispatch.push_str(&dispatch_arm(&format!("0x{:02X}", opcode), instr, "input"));
+    }
+    dispatch.push_str(
+        "        _ => Err(nom::Err::Error(WasmDecodeError::illegal_opcode(opcode_start, opcode))),\n    }\n}\n",
+    );
+
+    let mut dispatch_fc = String::from(
+        "fn decode_leaf_fc<'t>(sub_opcode: u32, input: &'t [u8]) -> IParserResult<'t, WasmInstruction> {\n    use WasmInstruction::*;\n    match sub_opcode {\n",
+    );
+    for (sub_opcode, instr) in &fc {
+        dispatch_fc.push_str(&dispatch_arm(&sub_opcode.to_string(), instr, "input"));
+    }
+    dispatch_fc.push_str(
+        "        _ => Err(nom::Err::Error(WasmDecodeError::invalid_encoding(\n            input,\n            format!(\"unknown 0xFC sub-opcode {}\", sub_opcode),\n        ))),\n    }\n}\n",
+    );
+
+    fs::write(
+        Path::new(&out_dir).join("instruction_variants.rs"),
+        variants,
+    )
+    .unwrap();
+    fs::write(Path::new(&out_dir).join("instruction_dispatch.rs"), dispatch).unwrap();
+    fs::write(
+        Path::new(&out_dir).join("instruction_dispatch_fc.rs"),
+        dispatch_fc,
+    )
+    .unwrap();
+}
@@ -26,235 +26,18 @@ pub enum WasmBlockType {
 }
 
 // TODO[minor] maybe group these instructions per category in seperate enums
-pub enum WasmInstruction {
-    // control instructions
-    Unreachable,
-    Nop,
-    Block {
-        block_type: WasmBlockType,
-        instructions: Vec<WasmInstruction>,
-    },
-    Loop {
-        block_type: WasmBlockType,
-        instructions: Vec<WasmInstruction>,
-    },
-    If {
-        block_type: WasmBlockType,
-        consequent: Vec<WasmInstruction>,
-        alternative: Vec<WasmInstruction>,
-    },
-    Jump {
-        label: u32,
-    },
-    JumpIf {
-        label: u32,
-    },
-    JumpTable {
-        locations: Vec<u32>,
-        label: u32,
-    },
-    Return,
-    Call {
-        function_index: u32,
-    },
-    CallIndirect {
-        type_index: u32,
-    },
-    // Parametric instructions
-    Drop,
-    Select,
-
-    // Variable instructions
-    LocalGet(u32),
-    LocalSet(u32),
-    LocalTee(u32),
-    GlobalGet(u32),
-    GlobalSet(u32),
-
-    // Memory instructions
-    I32Load(WasmMemoryArg),
-    I64Load(WasmMemoryArg),
-    F32Load(WasmMemoryArg),
-    F64Load(WasmMemoryArg),
-    I32Load8S(WasmMemoryArg),
-    I32Load8U(WasmMemoryArg),
-    I32Load16S(WasmMemoryArg),
-    I32Load16U(WasmMemoryArg),
-    I64Load8S(WasmMemoryArg),
-    I64Load8U(WasmMemoryArg),
-    I64Load16S(WasmMemoryArg),
-    I64Load16U(WasmMemoryArg),
-    I64Load32S(WasmMemoryArg),
-    I64Load32U(WasmMemoryArg),
-    I32Store(WasmMemoryArg),
-    I64Store(WasmMemoryArg),
-    F32Store(WasmMemoryArg),
-    F64Store(WasmMemoryArg),
-    I32Store8(WasmMemoryArg),
-    I32Store16(WasmMemoryArg),
-    I64Store8(WasmMemoryArg),
-    I64Store16(WasmMemoryArg),
-    I64Store32(WasmMemoryArg),
-    MemorySize,
-    Memorygrow,
-
-    // numeric instructions
-    I32Const(i32),
-    I64Const(i64),
-    F32Const(f32),
-    F64Const(f64),
-
-    I32Eqz,
-    I32Eq,
-    I32Ne,
-    I32LtS,
-    I32LtU,
-    I32GtS,
-    I32GtU,
-    I32LeS,
-    I32LeU,
-    I32GeS,
-    I32GeU,
-
-    I64Eqz,
-    I64Eq,
-    I64Ne,
-    I64LtS,
-    I64LtU,
-    I64GtS,
-    I64GtU,
-    I64LeS,
-    I64LeU,
-    I64GeS,
-    I64GeU,
-
-    F32Eq,
-    F32Ne,
-    F32Lt,
-    F32Gt,
-    F32Le,
-    F32Ge,
-
-    F64Eq,
-    F64Ne,
-    F64Lt,
-    F64Gt,
-    F64Le,
-    F64Ge,
-
-    I32Clz,
-    I32Ctz,
-    I32Popcnt,
-    I32Add,
-    I32Sub,
-    I32Mul,
-    I32DivS,
-    I32DivU,
-    I32RemS,
-    I32RemU,
-    I32And,
-    I32Or,
-    I32Xor,
-    I32Shl,
-    I32ShrS,
-    I32ShrU,
-    I32Rotl,
-    I32Rotr,
-
-    I64Clz,
-    I64Ctz,
-    I64Popcnt,
-    I64Add,
-    I64Sub,
-    I64Mul,
-    I64DivS,
-    I64DivU,
-    I64RemS,
-    I64RemU,
-    I64And,
-    I64Or,
-    I64Xor,
-    I64Shl,
-    I64ShrS,
-    I64ShrU,
-    I64Rotl,
-    I64Rotr,
-
-    F32Abs,
-    F32Neg,
-    F32Ceil,
-    F32Floor,
-    F32Trunc,
-    F32Nearest,
-    F32Sqrt,
-    F32Add,
-    F32Sub,
-    F32Mul,
-    F32Div,
-    F32Min,
-    F32Max,
-    F32Copysign,
-
-    F64Abs,
-    F64Neg,
-    F64Ceil,
-    F64Floor,
-    F64Trunc,
-    F64Nearest,
-    F64Sqrt,
-    F64Add,
-    F64Sub,
-    F64Mul,
-    F64Div,
-    F64Min,
-    F64Max,
-    F64Copysign,
-
-    I32WrapI64,
-    I32TruncF32S,
-    I32TruncF32U,
-    I32TruncF64S,
-    I32TruncF64U,
-    I64ExtendI32S,
-    I64ExtendI32U,
-    I64TruncF32S,
-    I64TruncF32U,
-    I64TruncF64S,
-    I64TruncF64U,
-    F32ConvertI32S,
-    F32ConvertI32U,
-    F32ConvertI64S,
-    F32ConvertI64U,
-    F32DemoteF64,
-    F64ConvertI32S,
-    F64ConvertI32U,
-    F64ConvertI64S,
-    F64ConvertI64U,
-    F64PromoteF32,
-    I32ReinterpretF32,
-    I64ReinterpretF64,
-    F32ReinterpretI32,
-    F64ReinterpretI64,
-
-    I32Extend8S,
-    I32Extend16S,
-    I64Extend8S,
-    I64Extend16S,
-    I64Extend32S,
-
-    I32TruncSatF32S,
-    I32TruncSatF32U,
-    I32TruncSatF64S,
-    I32TruncSatF64U,
-    I64TruncSatF32S,
-    I64TruncSatF32U,
-    I64TruncSatF64S,
-    I64TruncSatF64U,
-}
+//
+// The whole enum (hand-written control-flow variants from build.rs's
+// CONTROL_VARIANTS and the leaf variants from instructions.in alike) is
+// generated by build.rs, since `include!` can only splice a complete item,
+// not a fragment into the middle of a hand-written `enum { .. }`; see
+// src/lib.rs's decode_leaf/decode_leaf_fc for the matching decoder.
+include!(concat!(env!("OUT_DIR"), "/instruction_variants.rs"));
 
+#[derive(Clone, Debug, PartialEq, Eq)]
 pub struct WasmMemoryArg {
-    align: u32,
-    offset: u32,
+    pub align: u32,
+    pub offset: u32,
 }
 
 #[derive(Clone, Debug, PartialEq, Eq)]
@@ -276,6 +59,67 @@ pub struct WasmTableType {
 }
 
 #[derive(Clone, Debug, PartialEq, Eq)]
+pub enum WasmImportDesc {
+    TypeIdx(u32),
+    TableType(WasmTableType),
+    MemType(WasmLimitType),
+    GlobalType(WasmGlobalType),
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct WasmImport {
+    pub module_name: String,
+    pub field_name: String,
+    pub desc: WasmImportDesc,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct WasmGlobal {
+    pub global_type: WasmGlobalType,
+    pub init: Vec<WasmInstruction>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum WasmExportDesc {
+    Func(u32),
+    Table(u32),
+    Mem(u32),
+    Global(u32),
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct WasmExport {
+    pub name: String,
+    pub desc: WasmExportDesc,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct WasmElement {
+    pub table_index: u32,
+    pub offset: Vec<WasmInstruction>,
+    pub function_indices: Vec<u32>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct WasmData<'t> {
+    pub memory_index: u32,
+    pub offset: Vec<WasmInstruction>,
+    pub bytes: &'t [u8],
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct WasmLocals {
+    pub count: u32,
+    pub value_type: WasmType,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct WasmFunctionBody {
+    pub locals: Vec<WasmLocals>,
+    pub body: Vec<WasmInstruction>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
 pub enum WasmSectionContent<'t> {
     CustomSection {
         name: String,
@@ -284,21 +128,62 @@ pub enum WasmSectionContent<'t> {
     TypeSection {
         types: Vec<WasmFunctionType>
     },
-    ImportSection,
-    FunctionSection,
-    TableSection,
-    MemorySection,
-    GlobalSection,
-    ExportSection,
-    StartSection,
-    ElementSection,
-    CodeSection,
-    DataSection,
+    ImportSection {
+        imports: Vec<WasmImport>,
+    },
+    FunctionSection {
+        type_indices: Vec<u32>,
+    },
+    TableSection {
+        tables: Vec<WasmTableType>,
+    },
+    MemorySection {
+        memories: Vec<WasmLimitType>,
+    },
+    GlobalSection {
+        globals: Vec<WasmGlobal>,
+    },
+    ExportSection {
+        exports: Vec<WasmExport>,
+    },
+    StartSection {
+        function_index: u32,
+    },
+    ElementSection {
+        elements: Vec<WasmElement>,
+    },
+    CodeSection {
+        functions: Vec<WasmFunctionBody>,
+    },
+    DataSection {
+        data: Vec<WasmData<'t>>,
+    },
     UnknownSection,
 }
 
-#[derive(Clone, Debug, PartialEq, Eq)]
+#[derive(Clone, Debug, PartialEq)]
 pub struct WasmSection<'t> {
-    size: u32,
-    content: WasmSectionContent<'t>,
+    pub size: u32,
+    pub content: WasmSectionContent<'t>,
+}
+
+/// A `namemap`: `(index, name)` pairs, in the order they appear.
+pub type WasmNameMap = Vec<(u32, String)>;
+
+/// An `indirectnamemap`: `(index, namemap)` pairs, in the order they
+/// appear.
+pub type WasmIndirectNameMap = Vec<(u32, WasmNameMap)>;
+
+/// Decoded payload of the well-known `"name"` custom section: debugging
+/// names for the module itself, its functions, and their locals. Any
+/// of the three may be absent, since each is its own optional
+/// subsection.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct WasmNameSection {
+    pub module_name: Option<String>,
+    /// `(function index, name)` pairs, in the order they appear.
+    pub function_names: WasmNameMap,
+    /// `(function index, [(local index, name), ...])` pairs, in the
+    /// order they appear.
+    pub local_names: WasmIndirectNameMap,
 }
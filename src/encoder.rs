@@ -0,0 +1,775 @@
+//! Inverse of the decoders in `lib.rs`: encodes the AST back into the
+//! binary format so a module can be decoded, transformed and written
+//! back out.
+
+use crate::ast::*;
+
+/// Implemented by every AST node that can be serialized back to the
+/// WebAssembly binary format.
+pub trait WasmEncode {
+    fn encode(&self, out: &mut Vec<u8>);
+}
+
+/// Encode an unsigned LEB128 integer: emit the low 7 bits of `value`,
+/// setting the continuation bit (`0x80`) while bits remain.
+fn encode_unsigned(mut value: u64, out: &mut Vec<u8>) {
+    loop {
+        let byte = (value & 0x7f) as u8;
+        value >>= 7;
+        if value == 0 {
+            out.push(byte);
+            break;
+        } else {
+            out.push(byte | 0x80);
+        }
+    }
+}
+
+/// Encode a signed LEB128 integer: emit 7-bit groups, stopping once the
+/// sign bit is correctly represented by the remaining value (`0` with
+/// bit 6 clear, or `-1` with bit 6 set).
+fn encode_signed(mut value: i64, out: &mut Vec<u8>) {
+    loop {
+        let byte = (value & 0x7f) as u8;
+        value >>= 7;
+        let sign_bit_set = (byte & 0x40) != 0;
+        if (value == 0 && !sign_bit_set) || (value == -1 && sign_bit_set) {
+            out.push(byte);
+            break;
+        } else {
+            out.push(byte | 0x80);
+        }
+    }
+}
+
+fn encode_name(s: &str, out: &mut Vec<u8>) {
+    encode_unsigned(s.len() as u64, out);
+    out.extend_from_slice(s.as_bytes());
+}
+
+fn encode_vec<T: WasmEncode>(items: &[T], out: &mut Vec<u8>) {
+    encode_unsigned(items.len() as u64, out);
+    for item in items {
+        item.encode(out);
+    }
+}
+
+/// Encode a sequence of instructions terminated by `end` (`0x0B`).
+fn encode_expr(instructions: &[WasmInstruction], out: &mut Vec<u8>) {
+    for instruction in instructions {
+        instruction.encode(out);
+    }
+    out.push(0x0B);
+}
+
+fn encode_memarg(memarg: &WasmMemoryArg, out: &mut Vec<u8>) {
+    encode_unsigned(memarg.align as u64, out);
+    encode_unsigned(memarg.offset as u64, out);
+}
+
+impl WasmEncode for u32 {
+    fn encode(&self, out: &mut Vec<u8>) {
+        encode_unsigned(*self as u64, out);
+    }
+}
+
+impl WasmEncode for WasmType {
+    fn encode(&self, out: &mut Vec<u8>) {
+        out.push(match self {
+            WasmType::I32 => 0x7F,
+            WasmType::I64 => 0x7E,
+            WasmType::F32 => 0x7D,
+            WasmType::F64 => 0x7C,
+            WasmType::Empty => 0x40,
+        });
+    }
+}
+
+impl WasmEncode for WasmElemType {
+    fn encode(&self, out: &mut Vec<u8>) {
+        match self {
+            WasmElemType::FuncRef => out.push(0x70),
+        }
+    }
+}
+
+impl WasmEncode for WasmGlobalType {
+    fn encode(&self, out: &mut Vec<u8>) {
+        match self {
+            WasmGlobalType::Const(t) => {
+                t.encode(out);
+                out.push(0x00);
+            }
+            WasmGlobalType::Var(t) => {
+                t.encode(out);
+                out.push(0x01);
+            }
+        }
+    }
+}
+
+impl WasmEncode for WasmBlockType {
+    fn encode(&self, out: &mut Vec<u8>) {
+        match self {
+            WasmBlockType::Empty => out.push(0x40),
+            WasmBlockType::Valtype(t) => t.encode(out),
+            WasmBlockType::TypeIndex(index) => encode_signed(*index as i64, out),
+        }
+    }
+}
+
+impl WasmEncode for WasmLimitType {
+    fn encode(&self, out: &mut Vec<u8>) {
+        match self.max {
+            None => {
+                out.push(0x00);
+                encode_unsigned(self.min as u64, out);
+            }
+            Some(max) => {
+                out.push(0x01);
+                encode_unsigned(self.min as u64, out);
+                encode_unsigned(max as u64, out);
+            }
+        }
+    }
+}
+
+impl WasmEncode for WasmTableType {
+    fn encode(&self, out: &mut Vec<u8>) {
+        self.elemtype.encode(out);
+        self.limits.encode(out);
+    }
+}
+
+impl WasmEncode for WasmFunctionType {
+    fn encode(&self, out: &mut Vec<u8>) {
+        out.push(0x60);
+        encode_vec(&self.parameter_types, out);
+        encode_vec(&self.result_types, out);
+    }
+}
+
+impl WasmEncode for WasmImportDesc {
+    fn encode(&self, out: &mut Vec<u8>) {
+        match self {
+            WasmImportDesc::TypeIdx(idx) => {
+                out.push(0x00);
+                idx.encode(out);
+            }
+            WasmImportDesc::TableType(t) => {
+                out.push(0x01);
+                t.encode(out);
+            }
+            WasmImportDesc::MemType(l) => {
+                out.push(0x02);
+                l.encode(out);
+            }
+            WasmImportDesc::GlobalType(g) => {
+                out.push(0x03);
+                g.encode(out);
+            }
+        }
+    }
+}
+
+impl WasmEncode for WasmImport {
+    fn encode(&self, out: &mut Vec<u8>) {
+        encode_name(&self.module_name, out);
+        encode_name(&self.field_name, out);
+        self.desc.encode(out);
+    }
+}
+
+impl WasmEncode for WasmGlobal {
+    fn encode(&self, out: &mut Vec<u8>) {
+        self.global_type.encode(out);
+        encode_expr(&self.init, out);
+    }
+}
+
+impl WasmEncode for WasmExportDesc {
+    fn encode(&self, out: &mut Vec<u8>) {
+        match self {
+            WasmExportDesc::Func(idx) => {
+                out.push(0x00);
+                idx.encode(out);
+            }
+            WasmExportDesc::Table(idx) => {
+                out.push(0x01);
+                idx.encode(out);
+            }
+            WasmExportDesc::Mem(idx) => {
+                out.push(0x02);
+                idx.encode(out);
+            }
+            WasmExportDesc::Global(idx) => {
+                out.push(0x03);
+                idx.encode(out);
+            }
+        }
+    }
+}
+
+impl WasmEncode for WasmExport {
+    fn encode(&self, out: &mut Vec<u8>) {
+        encode_name(&self.name, out);
+        self.desc.encode(out);
+    }
+}
+
+impl WasmEncode for WasmElement {
+    fn encode(&self, out: &mut Vec<u8>) {
+        self.table_index.encode(out);
+        encode_expr(&self.offset, out);
+        encode_vec(&self.function_indices, out);
+    }
+}
+
+impl WasmEncode for WasmData<'_> {
+    fn encode(&self, out: &mut Vec<u8>) {
+        self.memory_index.encode(out);
+        encode_expr(&self.offset, out);
+        encode_unsigned(self.bytes.len() as u64, out);
+        out.extend_from_slice(self.bytes);
+    }
+}
+
+impl WasmEncode for WasmLocals {
+    fn encode(&self, out: &mut Vec<u8>) {
+        encode_unsigned(self.count as u64, out);
+        self.value_type.encode(out);
+    }
+}
+
+impl WasmEncode for WasmFunctionBody {
+    fn encode(&self, out: &mut Vec<u8>) {
+        let mut body = Vec::new();
+        encode_vec(&self.locals, &mut body);
+        encode_expr(&self.body, &mut body);
+        encode_unsigned(body.len() as u64, out);
+        out.extend_from_slice(&body);
+    }
+}
+
+impl WasmEncode for WasmInstruction {
+    fn encode(&self, out: &mut Vec<u8>) {
+        use WasmInstruction::*;
+        match self {
+            Unreachable => out.push(0x00),
+            Nop => out.push(0x01),
+            Block {
+                block_type,
+                instructions,
+            } => {
+                out.push(0x02);
+                block_type.encode(out);
+                encode_expr(instructions, out);
+            }
+            Loop {
+                block_type,
+                instructions,
+            } => {
+                out.push(0x03);
+                block_type.encode(out);
+                encode_expr(instructions, out);
+            }
+            If {
+                block_type,
+                consequent,
+                alternative,
+            } => {
+                out.push(0x04);
+                block_type.encode(out);
+                for instruction in consequent {
+                    instruction.encode(out);
+                }
+                if !alternative.is_empty() {
+                    out.push(0x05);
+                    for instruction in alternative {
+                        instruction.encode(out);
+                    }
+                }
+                out.push(0x0B);
+            }
+            Jump { label } => {
+                out.push(0x0C);
+                label.encode(out);
+            }
+            JumpIf { label } => {
+                out.push(0x0D);
+                label.encode(out);
+            }
+            JumpTable { locations, label } => {
+                out.push(0x0E);
+                encode_vec(locations, out);
+                label.encode(out);
+            }
+            Return => out.push(0x0F),
+            Call { function_index } => {
+                out.push(0x10);
+                function_index.encode(out);
+            }
+            CallIndirect { type_index } => {
+                out.push(0x11);
+                type_index.encode(out);
+                out.push(0x00);
+            }
+            Drop => out.push(0x1A),
+            Select => out.push(0x1B),
+            LocalGet(idx) => {
+                out.push(0x20);
+                idx.encode(out);
+            }
+            LocalSet(idx) => {
+                out.push(0x21);
+                idx.encode(out);
+            }
+            LocalTee(idx) => {
+                out.push(0x22);
+                idx.encode(out);
+            }
+            GlobalGet(idx) => {
+                out.push(0x23);
+                idx.encode(out);
+            }
+            GlobalSet(idx) => {
+                out.push(0x24);
+                idx.encode(out);
+            }
+            I32Load(m) => {
+                out.push(0x28);
+                encode_memarg(m, out);
+            }
+            I64Load(m) => {
+                out.push(0x29);
+                encode_memarg(m, out);
+            }
+            F32Load(m) => {
+                out.push(0x2A);
+                encode_memarg(m, out);
+            }
+            F64Load(m) => {
+                out.push(0x2B);
+                encode_memarg(m, out);
+            }
+            I32Load8S(m) => {
+                out.push(0x2C);
+                encode_memarg(m, out);
+            }
+            I32Load8U(m) => {
+                out.push(0x2D);
+                encode_memarg(m, out);
+            }
+            I32Load16S(m) => {
+                out.push(0x2E);
+                encode_memarg(m, out);
+            }
+            I32Load16U(m) => {
+                out.push(0x2F);
+                encode_memarg(m, out);
+            }
+            I64Load8S(m) => {
+                out.push(0x30);
+                encode_memarg(m, out);
+            }
+            I64Load8U(m) => {
+                out.push(0x31);
+                encode_memarg(m, out);
+            }
+            I64Load16S(m) => {
+                out.push(0x32);
+                encode_memarg(m, out);
+            }
+            I64Load16U(m) => {
+                out.push(0x33);
+                encode_memarg(m, out);
+            }
+            I64Load32S(m) => {
+                out.push(0x34);
+                encode_memarg(m, out);
+            }
+            I64Load32U(m) => {
+                out.push(0x35);
+                encode_memarg(m, out);
+            }
+            I32Store(m) => {
+                out.push(0x36);
+                encode_memarg(m, out);
+            }
+            I64Store(m) => {
+                out.push(0x37);
+                encode_memarg(m, out);
+            }
+            F32Store(m) => {
+                out.push(0x38);
+                encode_memarg(m, out);
+            }
+            F64Store(m) => {
+                out.push(0x39);
+                encode_memarg(m, out);
+            }
+            I32Store8(m) => {
+                out.push(0x3A);
+                encode_memarg(m, out);
+            }
+            I32Store16(m) => {
+                out.push(0x3B);
+                encode_memarg(m, out);
+            }
+            I64Store8(m) => {
+                out.push(0x3C);
+                encode_memarg(m, out);
+            }
+            I64Store16(m) => {
+                out.push(0x3D);
+                encode_memarg(m, out);
+            }
+            I64Store32(m) => {
+                out.push(0x3E);
+                encode_memarg(m, out);
+            }
+            MemorySize => {
+                out.push(0x3F);
+                out.push(0x00);
+            }
+            Memorygrow => {
+                out.push(0x40);
+                out.push(0x00);
+            }
+            I32Const(v) => {
+                out.push(0x41);
+                encode_signed(*v as i64, out);
+            }
+            I64Const(v) => {
+                out.push(0x42);
+                encode_signed(*v, out);
+            }
+            F32Const(v) => {
+                out.push(0x43);
+                out.extend_from_slice(&v.to_le_bytes());
+            }
+            F64Const(v) => {
+                out.push(0x44);
+                out.extend_from_slice(&v.to_le_bytes());
+            }
+            I32Eqz => out.push(0x45),
+            I32Eq => out.push(0x46),
+            I32Ne => out.push(0x47),
+            I32LtS => out.push(0x48),
+            I32LtU => out.push(0x49),
+            I32GtS => out.push(0x4A),
+            I32GtU => out.push(0x4B),
+            I32LeS => out.push(0x4C),
+            I32LeU => out.push(0x4D),
+            I32GeS => out.push(0x4E),
+            I32GeU => out.push(0x4F),
+            I64Eqz => out.push(0x50),
+            I64Eq => out.push(0x51),
+            I64Ne => out.push(0x52),
+            I64LtS => out.push(0x53),
+            I64LtU => out.push(0x54),
+            I64GtS => out.push(0x55),
+            I64GtU => out.push(0x56),
+            I64LeS => out.push(0x57),
+            I64LeU => out.push(0x58),
+            I64GeS => out.push(0x59),
+            I64GeU => out.push(0x5A),
+            F32Eq => out.push(0x5B),
+            F32Ne => out.push(0x5C),
+            F32Lt => out.push(0x5D),
+            F32Gt => out.push(0x5E),
+            F32Le => out.push(0x5F),
+            F32Ge => out.push(0x60),
+            F64Eq => out.push(0x61),
+            F64Ne => out.push(0x62),
+            F64Lt => out.push(0x63),
+            F64Gt => out.push(0x64),
+            F64Le => out.push(0x65),
+            F64Ge => out.push(0x66),
+            I32Clz => out.push(0x67),
+            I32Ctz => out.push(0x68),
+            I32Popcnt => out.push(0x69),
+            I32Add => out.push(0x6A),
+            I32Sub => out.push(0x6B),
+            I32Mul => out.push(0x6C),
+            I32DivS => out.push(0x6D),
+            I32DivU => out.push(0x6E),
+            I32RemS => out.push(0x6F),
+            I32RemU => out.push(0x70),
+            I32And => out.push(0x71),
+            I32Or => out.push(0x72),
+            I32Xor => out.push(0x73),
+            I32Shl => out.push(0x74),
+            I32ShrS => out.push(0x75),
+            I32ShrU => out.push(0x76),
+            I32Rotl => out.push(0x77),
+            I32Rotr => out.push(0x78),
+            I64Clz => out.push(0x79),
+            I64Ctz => out.push(0x7A),
+            I64Popcnt => out.push(0x7B),
+            I64Add => out.push(0x7C),
+            I64Sub => out.push(0x7D),
+            I64Mul => out.push(0x7E),
+            I64DivS => out.push(0x7F),
+            I64DivU => out.push(0x80),
+            I64RemS => out.push(0x81),
+            I64RemU => out.push(0x82),
+            I64And => out.push(0x83),
+            I64Or => out.push(0x84),
+            I64Xor => out.push(0x85),
+            I64Shl => out.push(0x86),
+            I64ShrS => out.push(0x87),
+            I64ShrU => out.push(0x88),
+            I64Rotl => out.push(0x89),
+            I64Rotr => out.push(0x8A),
+            F32Abs => out.push(0x8B),
+            F32Neg => out.push(0x8C),
+            F32Ceil => out.push(0x8D),
+            F32Floor => out.push(0x8E),
+            F32Trunc => out.push(0x8F),
+            F32Nearest => out.push(0x90),
+            F32Sqrt => out.push(0x91),
+            F32Add => out.push(0x92),
+            F32Sub => out.push(0x93),
+            F32Mul => out.push(0x94),
+            F32Div => out.push(0x95),
+            F32Min => out.push(0x96),
+            F32Max => out.push(0x97),
+            F32Copysign => out.push(0x98),
+            F64Abs => out.push(0x99),
+            F64Neg => out.push(0x9A),
+            F64Ceil => out.push(0x9B),
+            F64Floor => out.push(0x9C),
+            F64Trunc => out.push(0x9D),
+            F64Nearest => out.push(0x9E),
+            F64Sqrt => out.push(0x9F),
+            F64Add => out.push(0xA0),
+            F64Sub => out.push(0xA1),
+            F64Mul => out.push(0xA2),
+            F64Div => out.push(0xA3),
+            F64Min => out.push(0xA4),
+            F64Max => out.push(0xA5),
+            F64Copysign => out.push(0xA6),
+            I32WrapI64 => out.push(0xA7),
+            I32TruncF32S => out.push(0xA8),
+            I32TruncF32U => out.push(0xA9),
+            I32TruncF64S => out.push(0xAA),
+            I32TruncF64U => out.push(0xAB),
+            I64ExtendI32S => out.push(0xAC),
+            I64ExtendI32U => out.push(0xAD),
+            I64TruncF32S => out.push(0xAE),
+            I64TruncF32U => out.push(0xAF),
+            I64TruncF64S => out.push(0xB0),
+            I64TruncF64U => out.push(0xB1),
+            F32ConvertI32S => out.push(0xB2),
+            F32ConvertI32U => out.push(0xB3),
+            F32ConvertI64S => out.push(0xB4),
+            F32ConvertI64U => out.push(0xB5),
+            F32DemoteF64 => out.push(0xB6),
+            F64ConvertI32S => out.push(0xB7),
+            F64ConvertI32U => out.push(0xB8),
+            F64ConvertI64S => out.push(0xB9),
+            F64ConvertI64U => out.push(0xBA),
+            F64PromoteF32 => out.push(0xBB),
+            I32ReinterpretF32 => out.push(0xBC),
+            I64ReinterpretF64 => out.push(0xBD),
+            F32ReinterpretI32 => out.push(0xBE),
+            F64ReinterpretI64 => out.push(0xBF),
+            I32Extend8S => out.push(0xC0),
+            I32Extend16S => out.push(0xC1),
+            I64Extend8S => out.push(0xC2),
+            I64Extend16S => out.push(0xC3),
+            I64Extend32S => out.push(0xC4),
+            I32TruncSatF32S => {
+                out.push(0xFC);
+                encode_unsigned(0, out);
+            }
+            I32TruncSatF32U => {
+                out.push(0xFC);
+                encode_unsigned(1, out);
+            }
+            I32TruncSatF64S => {
+                out.push(0xFC);
+                encode_unsigned(2, out);
+            }
+            I32TruncSatF64U => {
+                out.push(0xFC);
+                encode_unsigned(3, out);
+            }
+            I64TruncSatF32S => {
+                out.push(0xFC);
+                encode_unsigned(4, out);
+            }
+            I64TruncSatF32U => {
+                out.push(0xFC);
+                encode_unsigned(5, out);
+            }
+            I64TruncSatF64S => {
+                out.push(0xFC);
+                encode_unsigned(6, out);
+            }
+            I64TruncSatF64U => {
+                out.push(0xFC);
+                encode_unsigned(7, out);
+            }
+        }
+    }
+}
+
+impl WasmSectionContent<'_> {
+    fn id(&self) -> u8 {
+        match self {
+            WasmSectionContent::CustomSection { .. } => 0x00,
+            WasmSectionContent::TypeSection { .. } => 0x01,
+            WasmSectionContent::ImportSection { .. } => 0x02,
+            WasmSectionContent::FunctionSection { .. } => 0x03,
+            WasmSectionContent::TableSection { .. } => 0x04,
+            WasmSectionContent::MemorySection { .. } => 0x05,
+            WasmSectionContent::GlobalSection { .. } => 0x06,
+            WasmSectionContent::ExportSection { .. } => 0x07,
+            WasmSectionContent::StartSection { .. } => 0x08,
+            WasmSectionContent::ElementSection { .. } => 0x09,
+            WasmSectionContent::CodeSection { .. } => 0x0A,
+            WasmSectionContent::DataSection { .. } => 0x0B,
+            WasmSectionContent::UnknownSection => {
+                unreachable!("an UnknownSection cannot be re-encoded")
+            }
+        }
+    }
+}
+
+impl WasmEncode for WasmSectionContent<'_> {
+    fn encode(&self, out: &mut Vec<u8>) {
+        match self {
+            WasmSectionContent::CustomSection { name, bytes } => {
+                encode_name(name, out);
+                out.extend_from_slice(bytes);
+            }
+            WasmSectionContent::TypeSection { types } => encode_vec(types, out),
+            WasmSectionContent::ImportSection { imports } => encode_vec(imports, out),
+            WasmSectionContent::FunctionSection { type_indices } => {
+                encode_vec(type_indices, out)
+            }
+            WasmSectionContent::TableSection { tables } => encode_vec(tables, out),
+            WasmSectionContent::MemorySection { memories } => encode_vec(memories, out),
+            WasmSectionContent::GlobalSection { globals } => encode_vec(globals, out),
+            WasmSectionContent::ExportSection { exports } => encode_vec(exports, out),
+            WasmSectionContent::StartSection { function_index } => {
+                function_index.encode(out)
+            }
+            WasmSectionContent::ElementSection { elements } => encode_vec(elements, out),
+            WasmSectionContent::CodeSection { functions } => encode_vec(functions, out),
+            WasmSectionContent::DataSection { data } => encode_vec(data, out),
+            WasmSectionContent::UnknownSection => {}
+        }
+    }
+}
+
+impl WasmEncode for WasmSection<'_> {
+    fn encode(&self, out: &mut Vec<u8>) {
+        let mut payload = Vec::new();
+        self.content.encode(&mut payload);
+        out.push(self.content.id());
+        encode_unsigned(payload.len() as u64, out);
+        out.extend_from_slice(&payload);
+    }
+}
+
+/// Encode a full module: the `\0asm` magic, the version, then every
+/// section in order.
+pub fn encode_module(sections: &[WasmSection]) -> Vec<u8> {
+    let mut out = Vec::new();
+    out.extend_from_slice(b"\0asm");
+    out.extend_from_slice(&[0x01, 0x00, 0x00, 0x00]);
+    for section in sections {
+        section.encode(&mut out);
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::module;
+
+    #[test]
+    fn test_round_trip() {
+        // \0asm, version 1, a Type section (one `() -> ()` function
+        // type), a Function section referencing it, and a Code section
+        // with one empty function body.
+        let fixture: Vec<u8> = vec![
+            0x00, 0x61, 0x73, 0x6D, 0x01, 0x00, 0x00, 0x00, 0x01, 0x04, 0x01, 0x60, 0x00, 0x00,
+            0x03, 0x02, 0x01, 0x00, 0x0A, 0x04, 0x01, 0x02, 0x00, 0x0B,
+        ];
+
+        let (rest, sections) = module(&fixture).expect("fixture should decode");
+        assert!(rest.is_empty());
+
+        let encoded = encode_module(&sections);
+        assert_eq!(encoded, fixture);
+
+        let (rest, sections_again) = module(&encoded).expect("re-encoded module should decode");
+        assert!(rest.is_empty());
+        assert_eq!(sections, sections_again);
+    }
+
+    /// `WasmInstruction`'s `WasmEncode` impl hand-writes an opcode for
+    /// every leaf instruction, independently of the table in
+    /// `instructions.in` that `decode_leaf`/`decode_leaf_fc` are
+    /// generated from. Reading the table here, rather than
+    /// transcribing it into this test, is what keeps the two from
+    /// silently drifting apart as opcodes are added or renumbered: a
+    /// zero-operand instance of every table entry is decoded, encoded,
+    /// and decoded again, and each step must reproduce the same bytes
+    /// decode_leaf expects at that opcode.
+    #[test]
+    fn test_every_leaf_instruction_round_trips() {
+        const TABLE: &str =
+            include_str!(concat!(env!("CARGO_MANIFEST_DIR"), "/instructions.in"));
+
+        let mut checked = 0;
+        for line in TABLE.lines() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let [opcode_tok, _variant, kind] =
+                line.split_whitespace().collect::<Vec<_>>()[..]
+            else {
+                panic!("instructions.in: malformed line `{}`", line);
+            };
+
+            let mut input = if let Some((prefix, sub)) = opcode_tok.split_once(':') {
+                assert_eq!(prefix, "0xFC");
+                vec![0xFC, sub.parse().expect("0xFC sub-opcode fits in a byte")]
+            } else {
+                vec![u8::from_str_radix(opcode_tok.trim_start_matches("0x"), 16).unwrap()]
+            };
+            input.extend_from_slice(match kind {
+                "none" => &[],
+                "reserved" => &[0x00],
+                "localidx" | "globalidx" => &[0x00],
+                "memarg" => &[0x00, 0x00],
+                "i32" | "i64" => &[0x00],
+                "f32" => &[0x00, 0x00, 0x00, 0x00],
+                "f64" => &[0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00],
+                other => panic!("instructions.in: unknown operand kind `{}`", other),
+            });
+
+            let (rest, decoded) =
+                crate::instruction(&input).unwrap_or_else(|e| panic!("{}: {:?}", opcode_tok, e));
+            assert!(rest.is_empty(), "{}: left over input after decoding", opcode_tok);
+
+            let mut encoded = Vec::new();
+            decoded.encode(&mut encoded);
+            assert_eq!(encoded, input, "{}: encode did not reproduce the decoded bytes", opcode_tok);
+
+            let (rest, decoded_again) = crate::instruction(&encoded).unwrap();
+            assert!(rest.is_empty());
+            assert_eq!(decoded, decoded_again, "{}: re-decoding the encoded bytes gave a different instruction", opcode_tok);
+
+            checked += 1;
+        }
+        assert_eq!(checked, 172, "instructions.in grew or shrank without this test noticing");
+    }
+}
@@ -0,0 +1,116 @@
+//! A decode error carrying the byte offset it occurred at, so malformed
+//! input produces a diagnosable error instead of a panic.
+
+use nom::error::{ErrorKind, ParseError};
+
+/// What went wrong while decoding.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum WasmDecodeErrorKind {
+    /// Ran out of bytes mid-field: the section or function body ended
+    /// before its declared contents were fully read.
+    UnexpectedEnd,
+    /// A section id, instruction opcode, or tag byte did not match any
+    /// known value.
+    IllegalOpcode(u8),
+    /// Bytes were present but did not form a valid encoding (e.g. a
+    /// LEB128 integer too long for its bit width, or invalid UTF-8).
+    InvalidEncoding(String),
+}
+
+/// A decode failure at a given position in the input.
+///
+/// `remaining` is the slice starting at the byte offset where decoding
+/// failed; since every intermediate slice parsers work with is a
+/// sub-slice of the buffer the caller originally passed to the
+/// top-level `module`/`Parser`, its position can be recovered with
+/// [`WasmDecodeError::offset`] once that original buffer is available.
+#[derive(Clone, Debug, PartialEq)]
+pub struct WasmDecodeError<'t> {
+    pub remaining: &'t [u8],
+    pub kind: WasmDecodeErrorKind,
+}
+
+impl<'t> WasmDecodeError<'t> {
+    pub fn eof(remaining: &'t [u8]) -> Self {
+        WasmDecodeError {
+            remaining,
+            kind: WasmDecodeErrorKind::UnexpectedEnd,
+        }
+    }
+
+    pub fn illegal_opcode(remaining: &'t [u8], opcode: u8) -> Self {
+        WasmDecodeError {
+            remaining,
+            kind: WasmDecodeErrorKind::IllegalOpcode(opcode),
+        }
+    }
+
+    pub fn invalid_encoding(remaining: &'t [u8], message: impl Into<String>) -> Self {
+        WasmDecodeError {
+            remaining,
+            kind: WasmDecodeErrorKind::InvalidEncoding(message.into()),
+        }
+    }
+
+    /// The absolute byte offset of this error within `original`, the
+    /// buffer originally handed to the parser.
+    pub fn offset(&self, original: &[u8]) -> usize {
+        self.remaining.as_ptr() as usize - original.as_ptr() as usize
+    }
+}
+
+impl<'t> std::fmt::Display for WasmDecodeError<'t> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match &self.kind {
+            WasmDecodeErrorKind::UnexpectedEnd => {
+                write!(f, "unexpected end of section or function")
+            }
+            WasmDecodeErrorKind::IllegalOpcode(byte) => {
+                write!(f, "illegal opcode 0x{:02X}", byte)
+            }
+            WasmDecodeErrorKind::InvalidEncoding(message) => {
+                write!(f, "invalid encoding: {}", message)
+            }
+        }
+    }
+}
+
+impl<'t> std::error::Error for WasmDecodeError<'t> {}
+
+impl<'t> ParseError<&'t [u8]> for WasmDecodeError<'t> {
+    fn from_error_kind(input: &'t [u8], kind: ErrorKind) -> Self {
+        match kind {
+            ErrorKind::Eof => WasmDecodeError::eof(input),
+            _ => WasmDecodeError::invalid_encoding(input, format!("{:?}", kind)),
+        }
+    }
+
+    fn append(_input: &'t [u8], _kind: ErrorKind, other: Self) -> Self {
+        other
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_offset_is_relative_to_the_original_buffer() {
+        let original = [0u8; 10];
+        let error = WasmDecodeError::illegal_opcode(&original[4..], 0xFF);
+        assert_eq!(error.offset(&original), 4);
+    }
+
+    #[test]
+    fn test_offset_reported_by_a_real_decode_error() {
+        // Six groups of 7 bits each is one more than a 32-bit value allows;
+        // the error should point at the 6th (index 5) byte, where the
+        // overlong encoding is detected.
+        let bytes = [0x80, 0x80, 0x80, 0x80, 0x80, 0x01];
+        let error = match crate::unsigned_int(32, &bytes) {
+            Err(nom::Err::Error(e)) => e,
+            other => panic!("expected an invalid-encoding error, got {:?}", other),
+        };
+        assert_eq!(error.offset(&bytes), 5);
+    }
+}
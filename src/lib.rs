@@ -1,8 +1,14 @@
-mod ast;
+pub mod ast;
+pub mod encoder;
+pub mod error;
+#[cfg(feature = "fuzzing")]
+pub mod fuzzing;
+pub mod parser;
 use crate::ast::*;
+use crate::error::WasmDecodeError;
 use nom::{
     branch::alt,
-    bytes::streaming::{tag, take},
+    bytes::complete::{tag, take},
     error::ParseError,
     multi::many_m_n,
     sequence::tuple,
@@ -10,14 +16,18 @@ use nom::{
 };
 
 type Input<'t> = &'t [u8];
-type IParserResult<'t, O> = IResult<Input<'t>, O>;
+type IParserResult<'t, O> = IResult<Input<'t>, O, WasmDecodeError<'t>>;
 
-trait Parser<I, O, E>: Fn(I) -> IResult<I, O, E> {}
-impl<I, O, E, F> Parser<I, O, E> for F
+/// A nameable alias for "a nom-style combinator function", so helpers
+/// like [`tag_`] and [`many_m`] can return `impl NomFn<..>` instead of a
+/// boxed trait object. Named `NomFn` rather than `Parser` so it doesn't
+/// collide with the public [`crate::parser::Parser`] streaming front end.
+trait NomFn<I, O, E>: FnMut(I) -> IResult<I, O, E> {}
+impl<I, O, E, F> NomFn<I, O, E> for F
 where
     I: Clone + PartialEq,
     E: ParseError<I>,
-    F: Fn(I) -> IResult<I, O, E>,
+    F: FnMut(I) -> IResult<I, O, E>,
 {
 }
 
@@ -37,7 +47,7 @@ impl<I, O, E> MapOutput<I, O, E> for IResult<I, O, E> {
 }
 
 /// takes 1 byte from the input stream and returns it
-fn take1(input: &[u8]) -> IParserResult<u8> {
+fn take1(input: &[u8]) -> IParserResult<'_, u8> {
     take(1u8)(input).map(|(rest, output)| (rest, output[0]))
 }
 
@@ -47,35 +57,53 @@ fn tag_return<'t, T: Clone>(t: u8, ret: T) -> impl Fn(&'t [u8]) -> IParserResult
         if byte == t {
             Ok((next, ret.clone()))
         } else {
-            Err(nom::Err::Error((next, nom::error::ErrorKind::Tag)))
+            Err(nom::Err::Error(WasmDecodeError::illegal_opcode(input, byte)))
         }
     }
 }
 
-fn tag_<'t>(t: u8) -> impl Parser<&'t [u8], u8, (&'t [u8], nom::error::ErrorKind)> {
+fn tag_<'t>(t: u8) -> impl NomFn<&'t [u8], u8, WasmDecodeError<'t>> {
     tag_return::<'t>(t, t)
 }
 
-fn many_m<I, O, E>(m: usize, parser: impl Parser<I, O, E>) -> impl Parser<I, Vec<O>, E>
+fn many_m<I, O, E>(m: usize, mut parser: impl NomFn<I, O, E>) -> impl NomFn<I, Vec<O>, E>
 where
-    I: Clone + PartialEq,
+    I: Clone + PartialEq + nom::InputLength,
     E: ParseError<I>,
 {
-    many_m_n(m, m, parser)
+    move |input: I| many_m_n(m, m, &mut parser)(input)
 }
 /* WASM VALUES */
 
-fn byte(input: &[u8]) -> IParserResult<u8> {
+fn byte(input: &[u8]) -> IParserResult<'_, u8> {
     take1(input)
 }
 
-/// Read an unsigned int.
-fn unsigned_int(size: usize, input: &[u8]) -> IParserResult<u64> {
+/// The maximum number of LEB128 groups that can encode an integer of
+/// `size` bits (7 payload bits per group).
+fn max_leb128_groups(size: usize) -> usize {
+    size.div_ceil(7)
+}
+
+/// Read an unsigned int of `size` bits, rejecting an encoding longer than
+/// the `max_leb128_groups(size)` groups such an integer needs. The unused
+/// high bits of the last group are not checked, so the result can be
+/// wider than `size` bits.
+fn unsigned_int(size: usize, input: &[u8]) -> IParserResult<'_, u64> {
+    let max_groups = max_leb128_groups(size);
     let mut next = input;
     let mut result = 0u64;
     let mut shift = 0u64;
+    let mut groups = 0;
     loop {
+        if groups == max_groups {
+            return Err(nom::Err::Error(WasmDecodeError::invalid_encoding(
+                next,
+                format!("LEB128 integer exceeds {} bits", size),
+            )));
+        }
         let (n, byte) = take1(next)?;
+        groups += 1;
         result |= ((byte & 0x7f) as u64) << shift;
         shift += 7;
         next = n;
@@ -85,14 +113,27 @@ fn unsigned_int(size: usize, input: &[u8]) -> IParserResult<u64> {
     }
 }
 
-/// Read a signed int
-fn signed_int(size: usize, input: &[u8]) -> IParserResult<i64> {
+/// Read a signed int of `size` bits, rejecting an encoding longer than
+/// the `max_leb128_groups(size)` groups such an integer needs. The unused
+/// high bits of the last group are not checked, and a value whose
+/// encoding reaches `size` bits is not sign-extended, so the result can
+/// be wider than `size` bits.
+fn signed_int(size: usize, input: &[u8]) -> IParserResult<'_, i64> {
+    let max_groups = max_leb128_groups(size);
     let mut next = input;
     let mut result = 0i64;
     let mut shift = 0u64;
+    let mut groups = 0;
 
     loop {
+        if groups == max_groups {
+            return Err(nom::Err::Error(WasmDecodeError::invalid_encoding(
+                next,
+                format!("LEB128 integer exceeds {} bits", size),
+            )));
+        }
         let (n, byte) = take1(next)?;
+        groups += 1;
         result |= ((byte & 0x7f) as i64) << shift;
         shift += 7;
         next = n;
@@ -108,22 +149,27 @@ fn signed_int(size: usize, input: &[u8]) -> IParserResult<i64> {
 }
 
 /// Reads the length of a vector
-fn vector_length(input: &[u8]) -> IParserResult<u32> {
+fn vector_length(input: &[u8]) -> IParserResult<'_, u32> {
     unsigned_int(32, input).map_output(|n| n as u32)
 }
 
 /// Read a name, this parser expects a length read from a previous
 /// parser.
-fn name(input: &[u8]) -> IParserResult<&str> {
+fn name(input: &[u8]) -> IParserResult<'_, &str> {
     let (next, length) = vector_length(input)?;
-    let (next, bytes) = take(length)(next)?;
-    // TODO: convert the error to a valid parser error
-    Ok((next, std::str::from_utf8(bytes).expect("a valid string")))
+    let (after, bytes) = take(length)(next)?;
+    match std::str::from_utf8(bytes) {
+        Ok(s) => Ok((after, s)),
+        Err(_) => Err(nom::Err::Error(WasmDecodeError::invalid_encoding(
+            next,
+            "name is not valid UTF-8",
+        ))),
+    }
 }
 
 /* WASM types */
 
-fn valtype(input: &[u8]) -> IParserResult<WasmType> {
+fn valtype(input: &[u8]) -> IParserResult<'_, WasmType> {
     alt((
         tag_return(0x7F, WasmType::I32),
         tag_return(0x7E, WasmType::I64),
@@ -132,12 +178,12 @@ fn valtype(input: &[u8]) -> IParserResult<WasmType> {
     ))(input)
 }
 
-fn resulttype(input: &[u8]) -> IParserResult<Vec<WasmType>> {
+fn resulttype(input: &[u8]) -> IParserResult<'_, Vec<WasmType>> {
     let (next, length) = vector_length(input)?;
     many_m(length as usize, valtype)(next)
 }
 
-fn functype(input: &[u8]) -> IParserResult<WasmFunctionType> {
+fn functype(input: &[u8]) -> IParserResult<'_, WasmFunctionType> {
     let (next, _) = tag_return(0x60, ())(input)?;
     let (next, (parameter_types, result_types)) = tuple((resulttype, resulttype))(next)?;
 
@@ -150,7 +196,7 @@ fn functype(input: &[u8]) -> IParserResult<WasmFunctionType> {
     ))
 }
 
-fn limits_without_max(input: &[u8]) -> IParserResult<WasmLimitType> {
+fn limits_without_max(input: &[u8]) -> IParserResult<'_, WasmLimitType> {
     let (next, _) = tag_return(0x00, ())(input)?;
     let (next, n) = unsigned_int(32, next)?;
     Ok((
@@ -162,7 +208,7 @@ fn limits_without_max(input: &[u8]) -> IParserResult<WasmLimitType> {
     ))
 }
 
-fn limits_with_max(input: &[u8]) -> IParserResult<WasmLimitType> {
+fn limits_with_max(input: &[u8]) -> IParserResult<'_, WasmLimitType> {
     let (next, _) = tag_return(0x01, ())(input)?;
     let (next, n) = unsigned_int(32, next)?;
     let (next, m) = unsigned_int(32, next)?;
@@ -175,11 +221,11 @@ fn limits_with_max(input: &[u8]) -> IParserResult<WasmLimitType> {
     ))
 }
 
-fn limits(input: &[u8]) -> IParserResult<WasmLimitType> {
+fn limits(input: &[u8]) -> IParserResult<'_, WasmLimitType> {
     alt((limits_without_max, limits_with_max))(input)
 }
 
-fn elemtype(input: &[u8]) -> IParserResult<WasmElemType> {
+fn elemtype(input: &[u8]) -> IParserResult<'_, WasmElemType> {
     // note, that there is only one elemtype,
     // however as we follow the spec, we create
     // a production rule for this as well, possibly
@@ -187,14 +233,14 @@ fn elemtype(input: &[u8]) -> IParserResult<WasmElemType> {
     tag_return(0x70, WasmElemType::FuncRef)(input)
 }
 
-fn tabletype(input: &[u8]) -> IParserResult<WasmTableType> {
+fn tabletype(input: &[u8]) -> IParserResult<'_, WasmTableType> {
     tuple((elemtype, limits))(input).map_output(|(et, lim)| WasmTableType {
         elemtype: et,
         limits: lim,
     })
 }
 
-fn globaltype(input: &[u8]) -> IParserResult<WasmGlobalType> {
+fn globaltype(input: &[u8]) -> IParserResult<'_, WasmGlobalType> {
     let (next, t) = valtype(input)?;
     let (next, m) = alt((tag_(0x00), tag_(0x01)))(next)?;
     Ok((
@@ -210,55 +256,209 @@ fn globaltype(input: &[u8]) -> IParserResult<WasmGlobalType> {
 
 /* Instructions */
 
-fn blocktype_empty(input: &[u8]) -> IParserResult<WasmBlockType> {
+fn blocktype_empty(input: &[u8]) -> IParserResult<'_, WasmBlockType> {
     tag_return(0x40, WasmBlockType::Empty)(input)
 }
 
-fn blocktype_valtype(input: &[u8]) -> IParserResult<WasmBlockType> {
+fn blocktype_valtype(input: &[u8]) -> IParserResult<'_, WasmBlockType> {
     valtype(input).map_output(|t| WasmBlockType::Valtype(t))
 }
 
-fn blocktype_typeindex(input: &[u8]) -> IParserResult<WasmBlockType> {
+fn blocktype_typeindex(input: &[u8]) -> IParserResult<'_, WasmBlockType> {
     signed_int(33, input).map_output(|index| WasmBlockType::TypeIndex(index as i32))
 }
 
-fn blocktype(input: &[u8]) -> IParserResult<WasmBlockType> {
+fn blocktype(input: &[u8]) -> IParserResult<'_, WasmBlockType> {
     alt((blocktype_empty, blocktype_valtype, blocktype_typeindex))(input)
 }
 
+fn memarg(input: &[u8]) -> IParserResult<'_, WasmMemoryArg> {
+    tuple((|i| unsigned_int(32, i), |i| unsigned_int(32, i)))(input).map_output(|(align, offset)| {
+        WasmMemoryArg {
+            align: align as u32,
+            offset: offset as u32,
+        }
+    })
+}
+
+/// Read a 4-byte little-endian IEEE-754 single precision float.
+fn f32(input: &[u8]) -> IParserResult<'_, f32> {
+    take(4u8)(input)
+        .map_output(|bytes: &[u8]| f32::from_le_bytes(bytes.try_into().expect("4 bytes")))
+}
+
+/// Read an 8-byte little-endian IEEE-754 double precision float.
+fn f64(input: &[u8]) -> IParserResult<'_, f64> {
+    take(8u8)(input)
+        .map_output(|bytes: &[u8]| f64::from_le_bytes(bytes.try_into().expect("8 bytes")))
+}
+
+/// Parse a single instruction, starting from its opcode byte.
+pub fn instruction(input: &[u8]) -> IParserResult<'_, WasmInstruction> {
+    instruction_body(input)
+}
+
+/// Parse the branches of an `if`: the consequent up to (and consuming) the
+/// `0x05` `else` or `0x0B` `end` opcode and, if `else` was seen, the
+/// alternative up to its `end`. Returns `(consequent, alternative)`, the
+/// alternative being empty when there was no `else`.
+fn if_branches(input: &[u8]) -> IParserResult<'_, (Vec<WasmInstruction>, Vec<WasmInstruction>)> {
+    let mut consequent = Vec::new();
+    let mut next = input;
+    loop {
+        let (_, opcode) = byte(next)?;
+        if opcode == 0x05 {
+            let (n, _) = take1(next)?;
+            let (n, alternative) = expr(n)?;
+            return Ok((n, (consequent, alternative)));
+        }
+        if opcode == 0x0B {
+            let (n, _) = take1(next)?;
+            return Ok((n, (consequent, Vec::new())));
+        }
+        let (n, instr) = instruction(next)?;
+        consequent.push(instr);
+        next = n;
+    }
+}
+
+/// Parse a sequence of instructions terminated by the `0x0B` `end` opcode.
+fn expr(input: &[u8]) -> IParserResult<'_, Vec<WasmInstruction>> {
+    let mut instructions = Vec::new();
+    let mut next = input;
+    loop {
+        let (_, opcode) = byte(next)?;
+        if opcode == 0x0B {
+            let (n, _) = take1(next)?;
+            next = n;
+            break;
+        }
+        let (n, instr) = instruction(next)?;
+        instructions.push(instr);
+        next = n;
+    }
+    Ok((next, instructions))
+}
+
+/// Parse one instruction, reading its opcode byte from the start of
+/// `input` so an illegal opcode is reported at the opcode's own offset.
+///
+/// Control-flow and call instructions are matched here by hand; the
+/// bulk of the arms (variable/memory/numeric instructions) are
+/// generated from `instructions.in` by `build.rs`, see
+/// `OUT_DIR/instruction_dispatch.rs` and `instruction_dispatch_fc.rs`.
+fn instruction_body<'t>(opcode_start: &'t [u8]) -> IParserResult<'t, WasmInstruction> {
+    use WasmInstruction::*;
+    let (input, opcode) = take1(opcode_start)?;
+    match opcode {
+        0x00 => Ok((input, Unreachable)),
+        0x01 => Ok((input, Nop)),
+        0x02 => {
+            let (next, block_type) = blocktype(input)?;
+            let (next, instructions) = expr(next)?;
+            Ok((
+                next,
+                Block {
+                    block_type,
+                    instructions,
+                },
+            ))
+        }
+        0x03 => {
+            let (next, block_type) = blocktype(input)?;
+            let (next, instructions) = expr(next)?;
+            Ok((
+                next,
+                Loop {
+                    block_type,
+                    instructions,
+                },
+            ))
+        }
+        0x04 => {
+            let (next, block_type) = blocktype(input)?;
+            let (next, (consequent, alternative)) = if_branches(next)?;
+            Ok((
+                next,
+                If {
+                    block_type,
+                    consequent,
+                    alternative,
+                },
+            ))
+        }
+        0x0C => labelidx(input).map_output(|label| Jump { label }),
+        0x0D => labelidx(input).map_output(|label| JumpIf { label }),
+        0x0E => {
+            let (next, length) = vector_length(input)?;
+            let (next, locations) = many_m(length as usize, labelidx)(next)?;
+            let (next, label) = labelidx(next)?;
+            Ok((next, JumpTable { locations, label }))
+        }
+        0x0F => Ok((input, Return)),
+        0x10 => funcidx(input).map_output(|function_index| Call { function_index }),
+        0x11 => {
+            let (next, type_index) = typeidx(input)?;
+            let (next, _) = tag_(0x00)(next)?;
+            Ok((next, CallIndirect { type_index }))
+        }
+        0xFC => {
+            let (next, sub_opcode) = unsigned_int(32, input)?;
+            decode_leaf_fc(sub_opcode as u32, next)
+        }
+        other => decode_leaf(other, opcode_start, input),
+    }
+}
+
+// `decode_leaf` (dispatch on the primary opcode byte) and `decode_leaf_fc`
+// (dispatch on the `0xFC` sub-opcode) are generated from instructions.in by
+// build.rs, as full standalone functions: `include!` can only splice a
+// complete item, not a fragment into the middle of this module's or
+// `instruction_body`'s hand-written `match { .. }`.
+include!(concat!(env!("OUT_DIR"), "/instruction_dispatch.rs"));
+include!(concat!(env!("OUT_DIR"), "/instruction_dispatch_fc.rs"));
+
 /* Modules */
 
-fn typeidx(input: &[u8]) -> IParserResult<u32> {
+fn typeidx(input: &[u8]) -> IParserResult<'_, u32> {
     unsigned_int(32, input).map_output(|idx| idx as u32)
 }
-fn funcidx(input: &[u8]) -> IParserResult<u32> {
+fn funcidx(input: &[u8]) -> IParserResult<'_, u32> {
     unsigned_int(32, input).map_output(|idx| idx as u32)
 }
-fn tableidx(input: &[u8]) -> IParserResult<u32> {
+fn tableidx(input: &[u8]) -> IParserResult<'_, u32> {
     unsigned_int(32, input).map_output(|idx| idx as u32)
 }
-fn memidx(input: &[u8]) -> IParserResult<u32> {
+fn memidx(input: &[u8]) -> IParserResult<'_, u32> {
     unsigned_int(32, input).map_output(|idx| idx as u32)
 }
-fn globalidx(input: &[u8]) -> IParserResult<u32> {
+fn globalidx(input: &[u8]) -> IParserResult<'_, u32> {
     unsigned_int(32, input).map_output(|idx| idx as u32)
 }
-fn localidx(input: &[u8]) -> IParserResult<u32> {
+fn localidx(input: &[u8]) -> IParserResult<'_, u32> {
     unsigned_int(32, input).map_output(|idx| idx as u32)
 }
-fn labelidx(input: &[u8]) -> IParserResult<u32> {
+fn labelidx(input: &[u8]) -> IParserResult<'_, u32> {
     unsigned_int(32, input).map_output(|idx| idx as u32)
 }
 
-fn section_id_size(id: u8, input: &[u8]) -> IParserResult<u32> {
+fn section_id_size(id: u8, input: &[u8]) -> IParserResult<'_, u32> {
     tuple((tag_(id), vector_length))(input).map_output(|(_, size)| size)
 }
 
-fn custom_section<'t>(input: &'t [u8]) -> IParserResult<WasmSectionContent<'t>> {
+fn custom_section<'t>(input: &'t [u8]) -> IParserResult<'t, WasmSectionContent<'t>> {
     let (next_begin, size) = section_id_size(0x00, input)?;
     let (next, name) = name(next_begin)?;
-    // subtract 4 for the length of the name, then subtract the length
-    let (next, bytes) = take(size - ((next_begin.len() - next.len()) as u32))(next)?;
+    // The declared size covers the name as well as the trailing bytes,
+    // so subtract off how much of it the name already consumed.
+    let name_len = (next_begin.len() - next.len()) as u32;
+    if name_len > size {
+        return Err(nom::Err::Error(WasmDecodeError::invalid_encoding(
+            next,
+            "custom section name is longer than the declared section size",
+        )));
+    }
+    let (next, bytes) = take(size - name_len)(next)?;
     Ok((
         next,
         WasmSectionContent::CustomSection {
@@ -268,15 +468,276 @@ fn custom_section<'t>(input: &'t [u8]) -> IParserResult<WasmSectionContent<'t>>
     ))
 }
 
-fn type_section<'t>(input: &'t [u8]) -> IParserResult<WasmSectionContent<'t>> {
+/// A `namemap`: a vector of `(index, name)` pairs, as used by the
+/// function- and local-name subsections.
+fn name_map(input: &[u8]) -> IParserResult<'_, WasmNameMap> {
+    let (next, length) = vector_length(input)?;
+    many_m(length as usize, |i| {
+        let (i, idx) = funcidx(i)?;
+        let (i, n) = name(i)?;
+        Ok((i, (idx, n.to_string())))
+    })(next)
+}
+
+/// An `indirectnamemap`: a vector of `(index, namemap)` pairs, used by
+/// the local-name subsection to map each function to its locals' names.
+fn indirect_name_map(input: &[u8]) -> IParserResult<'_, WasmIndirectNameMap> {
+    let (next, length) = vector_length(input)?;
+    many_m(length as usize, |i| {
+        let (i, idx) = funcidx(i)?;
+        let (i, names) = name_map(i)?;
+        Ok((i, (idx, names)))
+    })(next)
+}
+
+/// Parse the payload of the `"name"` custom section: a sequence of
+/// `(id: u8, size: u32, body)` subsections, ordered by increasing id.
+/// Any subsection may be absent, and unknown ids are skipped, so this
+/// walks by `size` rather than assuming which ids are present.
+fn name_subsections(input: &[u8]) -> IParserResult<'_, WasmNameSection> {
+    let mut result = WasmNameSection::default();
+    let mut next = input;
+    while !next.is_empty() {
+        let (n, id) = byte(next)?;
+        let (n, size) = vector_length(n)?;
+        let (n, body) = take(size)(n)?;
+        match id {
+            0x00 => result.module_name = Some(name(body)?.1.to_string()),
+            0x01 => result.function_names = name_map(body)?.1,
+            0x02 => result.local_names = indirect_name_map(body)?.1,
+            _ => {}
+        }
+        next = n;
+    }
+    Ok((next, result))
+}
+
+/// If `content` is the custom section named `"name"`, decode its bytes
+/// into a [`WasmNameSection`]; any other custom section yields `None`.
+pub fn name_section<'t>(
+    content: &WasmSectionContent<'t>,
+) -> Option<IParserResult<'t, WasmNameSection>> {
+    match content {
+        WasmSectionContent::CustomSection { name, bytes } if name == "name" => {
+            Some(name_subsections(bytes))
+        }
+        _ => None,
+    }
+}
+
+fn type_section<'t>(input: &'t [u8]) -> IParserResult<'t, WasmSectionContent<'t>> {
     let (next, _) = section_id_size(0x01, input)?;
     let (next, length) = vector_length(next)?;
     let (next, functypes) = many_m(length as usize, functype)(next)?;
     Ok((next, WasmSectionContent::TypeSection { types: functypes }))
 }
 
-fn sections<'t>(input: &'t [u8]) -> IParserResult<WasmSection<'t>> {
-    todo!()
+fn importdesc(input: &[u8]) -> IParserResult<'_, WasmImportDesc> {
+    let (next, tag) = take1(input)?;
+    match tag {
+        0x00 => typeidx(next).map_output(WasmImportDesc::TypeIdx),
+        0x01 => tabletype(next).map_output(WasmImportDesc::TableType),
+        0x02 => limits(next).map_output(WasmImportDesc::MemType),
+        0x03 => globaltype(next).map_output(WasmImportDesc::GlobalType),
+        _ => Err(nom::Err::Error(WasmDecodeError::illegal_opcode(
+            input, tag,
+        ))),
+    }
+}
+
+fn import(input: &[u8]) -> IParserResult<'_, WasmImport> {
+    let (next, module_name) = name(input)?;
+    let (next, field_name) = name(next)?;
+    let (next, desc) = importdesc(next)?;
+    Ok((
+        next,
+        WasmImport {
+            module_name: module_name.to_string(),
+            field_name: field_name.to_string(),
+            desc,
+        },
+    ))
+}
+
+fn import_section<'t>(input: &'t [u8]) -> IParserResult<'t, WasmSectionContent<'t>> {
+    let (next, _) = section_id_size(0x02, input)?;
+    let (next, length) = vector_length(next)?;
+    let (next, imports) = many_m(length as usize, import)(next)?;
+    Ok((next, WasmSectionContent::ImportSection { imports }))
+}
+
+fn function_section<'t>(input: &'t [u8]) -> IParserResult<'t, WasmSectionContent<'t>> {
+    let (next, _) = section_id_size(0x03, input)?;
+    let (next, length) = vector_length(next)?;
+    let (next, type_indices) = many_m(length as usize, typeidx)(next)?;
+    Ok((next, WasmSectionContent::FunctionSection { type_indices }))
+}
+
+fn table_section<'t>(input: &'t [u8]) -> IParserResult<'t, WasmSectionContent<'t>> {
+    let (next, _) = section_id_size(0x04, input)?;
+    let (next, length) = vector_length(next)?;
+    let (next, tables) = many_m(length as usize, tabletype)(next)?;
+    Ok((next, WasmSectionContent::TableSection { tables }))
+}
+
+fn memory_section<'t>(input: &'t [u8]) -> IParserResult<'t, WasmSectionContent<'t>> {
+    let (next, _) = section_id_size(0x05, input)?;
+    let (next, length) = vector_length(next)?;
+    let (next, memories) = many_m(length as usize, limits)(next)?;
+    Ok((next, WasmSectionContent::MemorySection { memories }))
+}
+
+fn global(input: &[u8]) -> IParserResult<'_, WasmGlobal> {
+    let (next, global_type) = globaltype(input)?;
+    let (next, init) = expr(next)?;
+    Ok((next, WasmGlobal { global_type, init }))
+}
+
+fn global_section<'t>(input: &'t [u8]) -> IParserResult<'t, WasmSectionContent<'t>> {
+    let (next, _) = section_id_size(0x06, input)?;
+    let (next, length) = vector_length(next)?;
+    let (next, globals) = many_m(length as usize, global)(next)?;
+    Ok((next, WasmSectionContent::GlobalSection { globals }))
+}
+
+fn exportdesc(input: &[u8]) -> IParserResult<'_, WasmExportDesc> {
+    let (next, tag) = take1(input)?;
+    match tag {
+        0x00 => funcidx(next).map_output(WasmExportDesc::Func),
+        0x01 => tableidx(next).map_output(WasmExportDesc::Table),
+        0x02 => memidx(next).map_output(WasmExportDesc::Mem),
+        0x03 => globalidx(next).map_output(WasmExportDesc::Global),
+        _ => Err(nom::Err::Error(WasmDecodeError::illegal_opcode(
+            input, tag,
+        ))),
+    }
+}
+
+fn export(input: &[u8]) -> IParserResult<'_, WasmExport> {
+    let (next, n) = name(input)?;
+    let (next, desc) = exportdesc(next)?;
+    Ok((
+        next,
+        WasmExport {
+            name: n.to_string(),
+            desc,
+        },
+    ))
+}
+
+fn export_section<'t>(input: &'t [u8]) -> IParserResult<'t, WasmSectionContent<'t>> {
+    let (next, _) = section_id_size(0x07, input)?;
+    let (next, length) = vector_length(next)?;
+    let (next, exports) = many_m(length as usize, export)(next)?;
+    Ok((next, WasmSectionContent::ExportSection { exports }))
+}
+
+fn start_section<'t>(input: &'t [u8]) -> IParserResult<'t, WasmSectionContent<'t>> {
+    let (next, _) = section_id_size(0x08, input)?;
+    let (next, function_index) = funcidx(next)?;
+    Ok((next, WasmSectionContent::StartSection { function_index }))
+}
+
+fn element(input: &[u8]) -> IParserResult<'_, WasmElement> {
+    let (next, table_index) = tableidx(input)?;
+    let (next, offset) = expr(next)?;
+    let (next, length) = vector_length(next)?;
+    let (next, function_indices) = many_m(length as usize, funcidx)(next)?;
+    Ok((
+        next,
+        WasmElement {
+            table_index,
+            offset,
+            function_indices,
+        },
+    ))
+}
+
+fn element_section<'t>(input: &'t [u8]) -> IParserResult<'t, WasmSectionContent<'t>> {
+    let (next, _) = section_id_size(0x09, input)?;
+    let (next, length) = vector_length(next)?;
+    let (next, elements) = many_m(length as usize, element)(next)?;
+    Ok((next, WasmSectionContent::ElementSection { elements }))
+}
+
+fn locals_entry(input: &[u8]) -> IParserResult<'_, WasmLocals> {
+    let (next, count) = vector_length(input)?;
+    let (next, value_type) = valtype(next)?;
+    Ok((next, WasmLocals { count, value_type }))
+}
+
+fn function_body(input: &[u8]) -> IParserResult<'_, WasmFunctionBody> {
+    let (next, size) = vector_length(input)?;
+    let (next, body_bytes) = take(size)(next)?;
+    let (rest, length) = vector_length(body_bytes)?;
+    let (rest, locals) = many_m(length as usize, locals_entry)(rest)?;
+    let (_, body) = expr(rest)?;
+    Ok((next, WasmFunctionBody { locals, body }))
+}
+
+fn code_section<'t>(input: &'t [u8]) -> IParserResult<'t, WasmSectionContent<'t>> {
+    let (next, _) = section_id_size(0x0A, input)?;
+    let (next, length) = vector_length(next)?;
+    let (next, functions) = many_m(length as usize, function_body)(next)?;
+    Ok((next, WasmSectionContent::CodeSection { functions }))
+}
+
+fn data<'t>(input: &'t [u8]) -> IParserResult<'t, WasmData<'t>> {
+    let (next, memory_index) = memidx(input)?;
+    let (next, offset) = expr(next)?;
+    let (next, length) = vector_length(next)?;
+    let (next, bytes) = take(length)(next)?;
+    Ok((
+        next,
+        WasmData {
+            memory_index,
+            offset,
+            bytes,
+        },
+    ))
+}
+
+fn data_section<'t>(input: &'t [u8]) -> IParserResult<'t, WasmSectionContent<'t>> {
+    let (next, _) = section_id_size(0x0B, input)?;
+    let (next, length) = vector_length(next)?;
+    let (next, segments) = many_m(length as usize, data)(next)?;
+    Ok((next, WasmSectionContent::DataSection { data: segments }))
+}
+
+pub fn sections<'t>(input: &'t [u8]) -> IParserResult<'t, WasmSection<'t>> {
+    let (_, id) = byte(input)?;
+    let (_, size) = section_id_size(id, input)?;
+    let (next, content) = match id {
+        0x00 => custom_section(input),
+        0x01 => type_section(input),
+        0x02 => import_section(input),
+        0x03 => function_section(input),
+        0x04 => table_section(input),
+        0x05 => memory_section(input),
+        0x06 => global_section(input),
+        0x07 => export_section(input),
+        0x08 => start_section(input),
+        0x09 => element_section(input),
+        0x0A => code_section(input),
+        0x0B => data_section(input),
+        _ => Err(nom::Err::Error(WasmDecodeError::illegal_opcode(input, id))),
+    }?;
+    Ok((next, WasmSection { size, content }))
+}
+
+/// Parse a full module: the `\0asm` magic, the version, then every
+/// section in the payload.
+pub fn module<'t>(input: &'t [u8]) -> IParserResult<'t, Vec<WasmSection<'t>>> {
+    let (next, _) = tag(&b"\0asm"[..])(input)?;
+    let (next, _) = tag(&[0x01, 0x00, 0x00, 0x00][..])(next)?;
+    let mut sects = Vec::new();
+    let mut rest = next;
+    while !rest.is_empty() {
+        let (n, section) = sections(rest)?;
+        sects.push(section);
+        rest = n;
+    }
+    Ok((rest, sects))
 }
 
 #[cfg(test)]
@@ -321,6 +782,31 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_unsigned_int_too_long() {
+        // Six groups of 7 bits each is one more than a 32-bit value allows.
+        let bytes = [0x80, 0x80, 0x80, 0x80, 0x80, 0x01];
+        assert_eq!(
+            unsigned_int(32, &bytes),
+            Err(nom::Err::Error(WasmDecodeError::invalid_encoding(
+                &bytes[5..],
+                "LEB128 integer exceeds 32 bits",
+            )))
+        );
+    }
+
+    #[test]
+    fn test_illegal_opcode() {
+        let bytes = [0x7F, 0x02]; // valtype, then an invalid globaltype mutability tag
+        assert_eq!(
+            globaltype(&bytes),
+            Err(nom::Err::Error(WasmDecodeError::illegal_opcode(
+                &bytes[1..],
+                0x02
+            )))
+        );
+    }
+
     #[test]
     fn test_custom_section() {
         let contents = vec![0x00, 0x8, 0x5, 104, 101, 108, 108, 111, 0xFF, 0xFE];
@@ -335,4 +821,204 @@ mod tests {
             ))
         );
     }
+
+    #[test]
+    fn test_custom_section_name_overruns_declared_size() {
+        // declared size 1, but the embedded name alone is 5 bytes
+        let contents = vec![0x00, 0x01, 0x05, b'h', b'e', b'l', b'l', b'o'];
+        assert_eq!(
+            custom_section(contents.as_ref()),
+            Err(nom::Err::Error(WasmDecodeError::invalid_encoding(
+                &contents[8..],
+                "custom section name is longer than the declared section size",
+            )))
+        );
+    }
+
+    #[test]
+    fn test_name_section() {
+        // module-name subsection (id 0): "hi"
+        // function-names subsection (id 1): [(0, "foo")]
+        let bytes = vec![
+            0x00, 0x03, 0x02, b'h', b'i', // id 0, size 3, name "hi"
+            0x01, 0x06, 0x01, 0x00, 0x03, b'f', b'o', b'o', // id 1, size 6, [(0, "foo")]
+        ];
+        let content = WasmSectionContent::CustomSection {
+            name: "name".to_string(),
+            bytes: bytes.as_ref(),
+        };
+        let (rest, decoded) = name_section(&content)
+            .expect("a name section")
+            .expect("should decode");
+        assert_eq!(rest, &[] as &[u8]);
+        assert_eq!(decoded.module_name, Some("hi".to_string()));
+        assert_eq!(decoded.function_names, vec![(0, "foo".to_string())]);
+        assert_eq!(decoded.local_names, vec![]);
+    }
+
+    #[test]
+    fn test_name_section_ignores_other_custom_sections() {
+        let content = WasmSectionContent::CustomSection {
+            name: "producers".to_string(),
+            bytes: &[],
+        };
+        assert!(name_section(&content).is_none());
+    }
+
+    #[test]
+    fn test_blocktype() {
+        assert_eq!(blocktype(&[0x40]), Ok((vec![].as_ref(), WasmBlockType::Empty)));
+        assert_eq!(
+            blocktype(&[0x7F]),
+            Ok((vec![].as_ref(), WasmBlockType::Valtype(WasmType::I32)))
+        );
+        assert_eq!(
+            blocktype(&[0x05]),
+            Ok((vec![].as_ref(), WasmBlockType::TypeIndex(5)))
+        );
+    }
+
+    #[test]
+    fn test_memarg() {
+        assert_eq!(
+            memarg(&[0x02, 0x10]),
+            Ok((
+                vec![].as_ref(),
+                WasmMemoryArg {
+                    align: 2,
+                    offset: 16,
+                }
+            ))
+        );
+    }
+
+    #[test]
+    fn test_f32() {
+        let bytes = 1.5f32.to_le_bytes();
+        assert_eq!(f32(&bytes), Ok((vec![].as_ref(), 1.5f32)));
+    }
+
+    #[test]
+    fn test_f64() {
+        let bytes = 2.5f64.to_le_bytes();
+        assert_eq!(f64(&bytes), Ok((vec![].as_ref(), 2.5f64)));
+    }
+
+    #[test]
+    fn test_instruction_block_and_loop() {
+        // block (empty blocktype) { nop } end
+        let bytes = [0x02, 0x40, 0x01, 0x0B];
+        assert_eq!(
+            instruction(&bytes),
+            Ok((
+                vec![].as_ref(),
+                WasmInstruction::Block {
+                    block_type: WasmBlockType::Empty,
+                    instructions: vec![WasmInstruction::Nop],
+                }
+            ))
+        );
+
+        // loop (empty blocktype) { nop } end
+        let bytes = [0x03, 0x40, 0x01, 0x0B];
+        assert_eq!(
+            instruction(&bytes),
+            Ok((
+                vec![].as_ref(),
+                WasmInstruction::Loop {
+                    block_type: WasmBlockType::Empty,
+                    instructions: vec![WasmInstruction::Nop],
+                }
+            ))
+        );
+    }
+
+    #[test]
+    fn test_instruction_if_else() {
+        // if (empty blocktype) { nop } else { nop } end
+        let bytes = [0x04, 0x40, 0x01, 0x05, 0x01, 0x0B];
+        assert_eq!(
+            instruction(&bytes),
+            Ok((
+                vec![].as_ref(),
+                WasmInstruction::If {
+                    block_type: WasmBlockType::Empty,
+                    consequent: vec![WasmInstruction::Nop],
+                    alternative: vec![WasmInstruction::Nop],
+                }
+            ))
+        );
+    }
+
+    #[test]
+    fn test_instruction_jump_table() {
+        // br_table [0, 1] 2
+        let bytes = [0x0E, 0x02, 0x00, 0x01, 0x02];
+        assert_eq!(
+            instruction(&bytes),
+            Ok((
+                vec![].as_ref(),
+                WasmInstruction::JumpTable {
+                    locations: vec![0, 1],
+                    label: 2,
+                }
+            ))
+        );
+    }
+
+    #[test]
+    fn test_instruction_memarg_leaf() {
+        // i32.load align=2 offset=16
+        let bytes = [0x28, 0x02, 0x10];
+        assert_eq!(
+            instruction(&bytes),
+            Ok((
+                vec![].as_ref(),
+                WasmInstruction::I32Load(WasmMemoryArg {
+                    align: 2,
+                    offset: 16,
+                })
+            ))
+        );
+    }
+
+    #[test]
+    fn test_instruction_f32_f64_const() {
+        let mut bytes = vec![0x43];
+        bytes.extend_from_slice(&1.5f32.to_le_bytes());
+        assert_eq!(
+            instruction(&bytes),
+            Ok((vec![].as_ref(), WasmInstruction::F32Const(1.5)))
+        );
+
+        let mut bytes = vec![0x44];
+        bytes.extend_from_slice(&2.5f64.to_le_bytes());
+        assert_eq!(
+            instruction(&bytes),
+            Ok((vec![].as_ref(), WasmInstruction::F64Const(2.5)))
+        );
+    }
+
+    #[test]
+    fn test_instruction_fc_opcode() {
+        // i32.trunc_sat_f32_s, the 0xFC:0 leaf instruction
+        let bytes = [0xFC, 0x00];
+        assert_eq!(
+            instruction(&bytes),
+            Ok((vec![].as_ref(), WasmInstruction::I32TruncSatF32S))
+        );
+    }
+
+    #[test]
+    fn test_expr() {
+        // nop; i32.const 1; end
+        let bytes = [0x01, 0x41, 0x01, 0x0B];
+        assert_eq!(
+            expr(&bytes),
+            Ok((
+                vec![].as_ref(),
+                vec![WasmInstruction::Nop, WasmInstruction::I32Const(1)]
+            ))
+        );
+    }
 }
@@ -0,0 +1,375 @@
+//! A pull-based, streaming front end on top of the leaf parsers in
+//! `lib.rs`. Unlike [`crate::module`], which eagerly decodes a whole
+//! module into owned `WasmSection`s, [`Parser`] yields one [`Payload`]
+//! event at a time and only parses a section's elements when the
+//! returned reader is iterated. This lets callers skip the elements of
+//! sections they are not interested in without paying for parsing them.
+//! The Code section's function bodies are decoded one per
+//! [`Parser::next_payload`] call, so a caller can stop before them, but
+//! reaching a section after the Code section decodes them all.
+
+use crate::ast::*;
+use crate::error::WasmDecodeError;
+use crate::{
+    byte, custom_section, data, element, export, funcidx, function_body, global, import,
+    section_id_size, tabletype, typeidx, vector_length, IParserResult, Input,
+};
+use nom::{bytes::complete::tag, sequence::tuple};
+
+/// A lazy reader over the elements of a section: it only parses the
+/// next element when asked for it.
+pub struct SectionReader<'t, T> {
+    remaining: Input<'t>,
+    count: u32,
+    read: fn(Input<'t>) -> IParserResult<'t, T>,
+}
+
+impl<'t, T> SectionReader<'t, T> {
+    fn new(count: u32, remaining: Input<'t>, read: fn(Input<'t>) -> IParserResult<'t, T>) -> Self {
+        SectionReader {
+            remaining,
+            count,
+            read,
+        }
+    }
+}
+
+impl<'t, T> Iterator for SectionReader<'t, T> {
+    type Item = IParserResult<'t, T>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.count == 0 {
+            return None;
+        }
+        match (self.read)(self.remaining) {
+            Ok((next, value)) => {
+                self.remaining = next;
+                self.count -= 1;
+                Some(Ok((next, value)))
+            }
+            Err(e) => {
+                self.count = 0;
+                Some(Err(e))
+            }
+        }
+    }
+}
+
+/// One event produced by [`Parser::next_payload`].
+pub enum Payload<'t> {
+    Version,
+    TypeSection(SectionReader<'t, WasmFunctionType>),
+    ImportSection(SectionReader<'t, WasmImport>),
+    FunctionSection(SectionReader<'t, u32>),
+    TableSection(SectionReader<'t, WasmTableType>),
+    MemorySection(SectionReader<'t, WasmLimitType>),
+    GlobalSection(SectionReader<'t, WasmGlobal>),
+    ExportSection(SectionReader<'t, WasmExport>),
+    StartSection { function_index: u32 },
+    ElementSection(SectionReader<'t, WasmElement>),
+    CodeSectionStart { count: u32 },
+    CodeSectionEntry(WasmFunctionBody),
+    DataSection(SectionReader<'t, WasmData<'t>>),
+    CustomSection { name: String, bytes: &'t [u8] },
+    End,
+}
+
+enum ParserState<'t> {
+    Start,
+    Body,
+    CodeSection {
+        remaining_count: u32,
+        body_bytes: Input<'t>,
+        after: Input<'t>,
+    },
+    Done,
+}
+
+/// A pull-based module parser, modeled on the `Parser`/`Payload` front
+/// end used by other streaming WebAssembly decoders: each call to
+/// [`Parser::next_payload`] consumes as little of the input as needed to
+/// produce the next [`Payload`].
+pub struct Parser<'t> {
+    remaining: Input<'t>,
+    state: ParserState<'t>,
+}
+
+impl<'t> Parser<'t> {
+    pub fn new(input: Input<'t>) -> Self {
+        Parser {
+            remaining: input,
+            state: ParserState::Start,
+        }
+    }
+
+    pub fn next_payload(&mut self) -> Option<IParserResult<'t, Payload<'t>>> {
+        match self.state {
+            ParserState::Start => self.next_start(),
+            ParserState::Body => self.next_body(),
+            ParserState::CodeSection { .. } => self.next_code_entry(),
+            ParserState::Done => None,
+        }
+    }
+
+    fn next_start(&mut self) -> Option<IParserResult<'t, Payload<'t>>> {
+        match tuple((tag(&b"\0asm"[..]), tag(&[0x01, 0x00, 0x00, 0x00][..])))(self.remaining) {
+            Ok((next, _)) => {
+                self.remaining = next;
+                self.state = ParserState::Body;
+                Some(Ok((next, Payload::Version)))
+            }
+            Err(e) => Some(Err(e)),
+        }
+    }
+
+    fn next_body(&mut self) -> Option<IParserResult<'t, Payload<'t>>> {
+        if self.remaining.is_empty() {
+            self.state = ParserState::Done;
+            return Some(Ok((self.remaining, Payload::End)));
+        }
+        match self.next_section() {
+            Ok((next, payload)) => {
+                self.remaining = next;
+                Some(Ok((next, payload)))
+            }
+            Err(e) => Some(Err(e)),
+        }
+    }
+
+    fn next_code_entry(&mut self) -> Option<IParserResult<'t, Payload<'t>>> {
+        let (remaining_count, body_bytes, after) = match self.state {
+            ParserState::CodeSection {
+                remaining_count,
+                body_bytes,
+                after,
+            } => (remaining_count, body_bytes, after),
+            _ => unreachable!(),
+        };
+        if remaining_count == 0 {
+            self.remaining = after;
+            self.state = ParserState::Body;
+            return self.next_body();
+        }
+        match function_body(body_bytes) {
+            Ok((next, body)) => {
+                self.state = ParserState::CodeSection {
+                    remaining_count: remaining_count - 1,
+                    body_bytes: next,
+                    after,
+                };
+                Some(Ok((after, Payload::CodeSectionEntry(body))))
+            }
+            Err(e) => Some(Err(e)),
+        }
+    }
+
+    /// Parse the header of the section starting at `self.remaining`,
+    /// returning the payload for it and the input position right after
+    /// the whole section (header included).
+    fn next_section(&mut self) -> IParserResult<'t, Payload<'t>> {
+        let (_, id) = byte(self.remaining)?;
+        let (header_end, size) = section_id_size(id, self.remaining)?;
+        let (after, content_bytes) = nom::bytes::complete::take(size)(header_end)?;
+
+        match id {
+            0x00 => {
+                let (_, content) = custom_section(self.remaining)?;
+                match content {
+                    WasmSectionContent::CustomSection { name, bytes } => {
+                        Ok((after, Payload::CustomSection { name, bytes }))
+                    }
+                    _ => unreachable!("custom_section always returns CustomSection"),
+                }
+            }
+            0x01 => {
+                let (rest, count) = vector_length(content_bytes)?;
+                Ok((
+                    after,
+                    Payload::TypeSection(SectionReader::new(count, rest, crate::functype)),
+                ))
+            }
+            0x02 => {
+                let (rest, count) = vector_length(content_bytes)?;
+                Ok((
+                    after,
+                    Payload::ImportSection(SectionReader::new(count, rest, import)),
+                ))
+            }
+            0x03 => {
+                let (rest, count) = vector_length(content_bytes)?;
+                Ok((
+                    after,
+                    Payload::FunctionSection(SectionReader::new(count, rest, typeidx)),
+                ))
+            }
+            0x04 => {
+                let (rest, count) = vector_length(content_bytes)?;
+                Ok((
+                    after,
+                    Payload::TableSection(SectionReader::new(count, rest, tabletype)),
+                ))
+            }
+            0x05 => {
+                let (rest, count) = vector_length(content_bytes)?;
+                Ok((
+                    after,
+                    Payload::MemorySection(SectionReader::new(count, rest, crate::limits)),
+                ))
+            }
+            0x06 => {
+                let (rest, count) = vector_length(content_bytes)?;
+                Ok((
+                    after,
+                    Payload::GlobalSection(SectionReader::new(count, rest, global)),
+                ))
+            }
+            0x07 => {
+                let (rest, count) = vector_length(content_bytes)?;
+                Ok((
+                    after,
+                    Payload::ExportSection(SectionReader::new(count, rest, export)),
+                ))
+            }
+            0x08 => {
+                let (_, function_index) = funcidx(content_bytes)?;
+                Ok((after, Payload::StartSection { function_index }))
+            }
+            0x09 => {
+                let (rest, count) = vector_length(content_bytes)?;
+                Ok((
+                    after,
+                    Payload::ElementSection(SectionReader::new(count, rest, element)),
+                ))
+            }
+            0x0A => {
+                let (rest, count) = vector_length(content_bytes)?;
+                self.state = ParserState::CodeSection {
+                    remaining_count: count,
+                    body_bytes: rest,
+                    after,
+                };
+                Ok((after, Payload::CodeSectionStart { count }))
+            }
+            0x0B => {
+                let (rest, count) = vector_length(content_bytes)?;
+                Ok((
+                    after,
+                    Payload::DataSection(SectionReader::new(count, rest, data)),
+                ))
+            }
+            _ => Err(nom::Err::Error(WasmDecodeError::illegal_opcode(
+                self.remaining,
+                id,
+            ))),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // \0asm, version 1, a Type section (one `() -> ()` function type), a
+    // Function section referencing it, and a Code section with one empty
+    // function body — the same fixture used by the encoder's round-trip test.
+    const FIXTURE: &[u8] = &[
+        0x00, 0x61, 0x73, 0x6D, 0x01, 0x00, 0x00, 0x00, 0x01, 0x04, 0x01, 0x60, 0x00, 0x00, 0x03,
+        0x02, 0x01, 0x00, 0x0A, 0x04, 0x01, 0x02, 0x00, 0x0B,
+    ];
+
+    #[test]
+    fn test_parser_drives_a_full_module() {
+        let mut parser = Parser::new(FIXTURE);
+
+        assert!(matches!(
+            parser.next_payload().unwrap().unwrap().1,
+            Payload::Version
+        ));
+
+        match parser.next_payload().unwrap().unwrap().1 {
+            Payload::TypeSection(reader) => {
+                let types: Vec<_> = reader.map(|r| r.unwrap().1).collect();
+                assert_eq!(
+                    types,
+                    vec![WasmFunctionType {
+                        parameter_types: vec![],
+                        result_types: vec![],
+                    }]
+                );
+            }
+            _ => panic!("expected a type section, got a different payload"),
+        }
+
+        match parser.next_payload().unwrap().unwrap().1 {
+            Payload::FunctionSection(reader) => {
+                let type_indices: Vec<_> = reader.map(|r| r.unwrap().1).collect();
+                assert_eq!(type_indices, vec![0]);
+            }
+            _ => panic!("expected a function section"),
+        }
+
+        match parser.next_payload().unwrap().unwrap().1 {
+            Payload::CodeSectionStart { count } => assert_eq!(count, 1),
+            _ => panic!("expected the start of a code section"),
+        }
+
+        match parser.next_payload().unwrap().unwrap().1 {
+            Payload::CodeSectionEntry(body) => {
+                assert_eq!(body.locals, vec![]);
+                assert_eq!(body.body, vec![]);
+            }
+            _ => panic!("expected a code section entry"),
+        }
+
+        assert!(matches!(parser.next_payload().unwrap().unwrap().1, Payload::End));
+        assert!(parser.next_payload().is_none());
+    }
+
+    #[test]
+    fn test_parser_can_skip_a_section_reader_without_iterating_it() {
+        // Not consuming the TypeSection's SectionReader at all must not
+        // prevent the parser from reaching the sections that follow it.
+        let mut parser = Parser::new(FIXTURE);
+        assert!(matches!(
+            parser.next_payload().unwrap().unwrap().1,
+            Payload::Version
+        ));
+        assert!(matches!(
+            parser.next_payload().unwrap().unwrap().1,
+            Payload::TypeSection(_)
+        ));
+        assert!(matches!(
+            parser.next_payload().unwrap().unwrap().1,
+            Payload::FunctionSection(_)
+        ));
+        assert!(matches!(
+            parser.next_payload().unwrap().unwrap().1,
+            Payload::CodeSectionStart { count: 1 }
+        ));
+    }
+
+    #[test]
+    fn test_parser_can_skip_the_code_section_by_stopping_early() {
+        // A caller uninterested in function bodies can simply stop asking
+        // for more payloads once it sees `CodeSectionStart`, without ever
+        // paying for `function_body` to run.
+        let mut parser = Parser::new(FIXTURE);
+        assert!(matches!(
+            parser.next_payload().unwrap().unwrap().1,
+            Payload::Version
+        ));
+        assert!(matches!(
+            parser.next_payload().unwrap().unwrap().1,
+            Payload::TypeSection(_)
+        ));
+        assert!(matches!(
+            parser.next_payload().unwrap().unwrap().1,
+            Payload::FunctionSection(_)
+        ));
+        assert!(matches!(
+            parser.next_payload().unwrap().unwrap().1,
+            Payload::CodeSectionStart { count: 1 }
+        ));
+        // Stop here: no CodeSectionEntry/End ever gets produced.
+    }
+}